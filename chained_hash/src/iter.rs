@@ -0,0 +1,65 @@
+use crate::ChainedHash;
+use hash_table::iter;
+
+pub use hash_table::chain::{IntoIter, Iter, IterMut};
+
+pub type Keys<'a, K, U> = iter::Keys<Iter<'a, K, U>>;
+
+pub type Values<'a, K, U> = iter::Values<Iter<'a, K, U>>;
+
+/// iterator over the entries removed from a ChainedHash by drain. The
+/// table is empty as soon as drain returns, whether or not this is
+/// run to completion
+pub type Drain<'a, K, U> = iter::Drain<'a, IntoIter<K, U>>;
+
+impl<K, U, S> ChainedHash<K, U, S> {
+    pub fn iter(&self) -> Iter<'_, K, U> {
+        Iter::new(&self.table)
+    }
+
+    pub fn iter_mut(&mut self) -> IterMut<'_, K, U> {
+        IterMut::new(&mut self.table)
+    }
+
+    pub fn keys(&self) -> Keys<'_, K, U> {
+        Keys::new(self.iter())
+    }
+
+    pub fn values(&self) -> Values<'_, K, U> {
+        Values::new(self.iter())
+    }
+
+    /// remove every entry, keeping the number of buckets
+    pub fn drain(&mut self) -> Drain<'_, K, U> {
+        let buckets = self.reset(self.get_capacity());
+        self.len = 0;
+        Drain::new(IntoIter::new(buckets))
+    }
+}
+
+impl<K, U, S> IntoIterator for ChainedHash<K, U, S> {
+    type Item = (K, U);
+    type IntoIter = IntoIter<K, U>;
+
+    fn into_iter(self) -> IntoIter<K, U> {
+        IntoIter::new(self.table)
+    }
+}
+
+impl<'a, K, U, S> IntoIterator for &'a ChainedHash<K, U, S> {
+    type Item = (&'a K, &'a U);
+    type IntoIter = Iter<'a, K, U>;
+
+    fn into_iter(self) -> Iter<'a, K, U> {
+        self.iter()
+    }
+}
+
+impl<'a, K, U, S> IntoIterator for &'a mut ChainedHash<K, U, S> {
+    type Item = (&'a K, &'a mut U);
+    type IntoIter = IterMut<'a, K, U>;
+
+    fn into_iter(self) -> IterMut<'a, K, U> {
+        self.iter_mut()
+    }
+}
@@ -1,89 +1,184 @@
 #![allow(dead_code)]
 
-use hash_table::{HashFn, HashTable, HashTableEntry, HashTableError};
+use hash_table::{HashFn, HashTable, HashTableError};
+use std::collections::hash_map::RandomState;
+use std::hash::Hash;
 use std::marker::PhantomData;
 
 use std::mem;
 
-// below inspired by https://rust-unofficial.github.io/too-many-lists/first-final.html
-// iterator impl is original code after some trial and error
-
-type Link<U> = Option<Box<ChainedHashEntry<U>>>;
-
-#[derive(Debug, PartialEq)]
-pub struct List<U> {
-    head: Link<U>,
-}
-
-#[derive(Debug, PartialEq)]
-struct ChainedHashEntry<U> {
-    data: HashTableEntry<U>,
-    next: Link<U>,
+mod entry;
+mod iter;
+
+pub use entry::{Entry, OccupiedEntry, VacantEntry};
+pub use hash_table::chain::List;
+pub use hash_table::entry::{Occupied, Vacant};
+pub use iter::{Drain, IntoIter, Iter, IterMut, Keys, Values};
+
+const DEFAULT_GROWTH_FACTOR: f32 = 2.0;
+
+pub struct ChainedHash<K, U, S = RandomState> {
+    table: Vec<List<K, U>>,
+    capacity: usize,
+    // number of entries across all chains
+    len: usize,
+    // grow the bucket vector once the average chain length would
+    // exceed this, None disables growing
+    max_load_factor: Option<f32>,
+    // the bucket vector is multiplied by this when growing
+    growth_factor: f32,
+    // hashes keys, a key is chained in the bucket its hash modulo
+    // capacity picks
+    hash_builder: S,
 }
 
-struct ChainedHash<U: std::cmp::PartialEq> {
-    table: Vec<List<U>>,
-    capacity: u16,
-}
+impl<K, U, S> ChainedHash<K, U, S> {
+    fn get_capacity(&self) -> usize {
+        self.capacity
+    }
 
-#[derive(Debug)]
-struct ChainedHashIterator<'a, U: std::cmp::PartialEq> {
-    next: &'a Link<U>,
+    /// replace the buckets with empty ones of the given capacity, handing
+    /// back the old buckets. len is left for the caller to account for
+    fn reset(&mut self, capacity: usize) -> Vec<List<K, U>> {
+        let mut table = Vec::with_capacity(capacity);
+        for _i in 0..capacity {
+            table.push(List { head: None });
+        }
+        self.capacity = capacity;
+        mem::replace(&mut self.table, table)
+    }
 }
 
-impl<'a, U: std::cmp::PartialEq + std::fmt::Debug> Iterator for ChainedHashIterator<'a, U> {
-    type Item = &'a Link<U>;
+impl<K: Hash + Eq, U, S: HashFn<K>> ChainedHash<K, U, S> {
+    /// get the entry for key for in-place manipulation. A vacant entry
+    /// appends to the end of the chain. Room for an absent key is made
+    /// up front, an existing key never grows the table
+    pub fn entry(&mut self, key: K) -> Entry<'_, K, U> {
+        if self.get(&key).is_none() {
+            self.reserve_one();
+        }
+        let x = self.hash(&key);
 
-    fn next(&mut self) -> Option<Self::Item> {
-        let current = self.next;
+        let mut link = &mut self.table[x].head;
+        while link.as_ref().is_some_and(|node| node.data.key != key) {
+            link = &mut link.as_mut().unwrap().next;
+        }
 
-        match &*current {
-            None => {
-                self.next = &None;
-                None
-            }
-            Some(node) => {
-                self.next = &node.next;
-                Some(current)
-            }
+        if link.is_some() {
+            Entry::Occupied(OccupiedEntry::new(link, &mut self.len))
+        } else {
+            Entry::Vacant(VacantEntry::new(link, &mut self.len, key))
         }
     }
 }
 
-impl<U: std::cmp::PartialEq> List<U> {
-    fn iter(&self) -> ChainedHashIterator<'_, U> {
-        ChainedHashIterator { next: &self.head }
+impl<K: Hash, U, S: HashFn<K>> ChainedHash<K, U, S> {
+    /// grow the bucket vector if one more entry would push the average
+    /// chain length over the maximum load factor. A table without
+    /// buckets gets one even when growing is disabled, there would be
+    /// no chain to add to otherwise
+    fn reserve_one(&mut self) {
+        if self.get_capacity() == 0 {
+            self.resize(1);
+        }
+        let max_load_factor = match self.max_load_factor {
+            Some(max_load_factor) => max_load_factor,
+            None => return,
+        };
+        if ((self.len + 1) as f32) > self.get_capacity() as f32 * max_load_factor {
+            let capacity = (self.get_capacity() as f32 * self.growth_factor).ceil() as usize;
+            let capacity = capacity.max(self.get_capacity() + 1);
+            self.resize(capacity);
+        }
     }
-}
 
-impl<U: std::cmp::PartialEq> ChainedHash<U> {
-    fn get_capacity(&self) -> u16 {
-        self.capacity
+    /// move every node into a bucket vector of the given capacity. The
+    /// nodes themselves are relinked, not reallocated
+    fn resize(&mut self, capacity: usize) {
+        let old = self.reset(capacity);
+        for mut list in old {
+            let mut current = list.head.take();
+            while let Some(mut node) = current {
+                current = node.next.take();
+                let x = self.hash(&node.data.key);
+                node.next = self.table[x].head.take();
+                self.table[x].head = Some(node);
+            }
+        }
     }
 }
 
-struct ChainedHashBuilder<U> {
+pub struct ChainedHashBuilder<K, U, S = RandomState> {
+    _phantom_k: PhantomData<K>,
     _phantom_u: PhantomData<U>,
-    capacity: u16,
+    capacity: usize,
+    max_load_factor: Option<f32>,
+    growth_factor: f32,
+    hash_builder: S,
 }
 
-impl<U: Default + std::cmp::PartialEq> ChainedHashBuilder<U> {
-    fn new() -> ChainedHashBuilder<U> {
-        ChainedHashBuilder::<U> {
+impl<K, U> ChainedHashBuilder<K, U> {
+    pub fn new() -> ChainedHashBuilder<K, U> {
+        ChainedHashBuilder::<K, U> {
+            _phantom_k: PhantomData {},
             _phantom_u: PhantomData {},
             capacity: 0,
+            max_load_factor: None,
+            growth_factor: DEFAULT_GROWTH_FACTOR,
+            hash_builder: RandomState::new(),
         }
     }
+}
 
-    pub fn with_capacity(mut self, capacity: u16) -> ChainedHashBuilder<U> {
+impl<K, U> Default for ChainedHashBuilder<K, U> {
+    fn default() -> ChainedHashBuilder<K, U> {
+        ChainedHashBuilder::new()
+    }
+}
+
+impl<K, U, S> ChainedHashBuilder<K, U, S> {
+    pub fn with_capacity(mut self, capacity: usize) -> ChainedHashBuilder<K, U, S> {
         self.capacity = capacity;
         self
     }
 
-    pub fn build(self) -> ChainedHash<U> {
-        let mut hash = ChainedHash::<U> {
-            table: Vec::with_capacity(self.capacity.into()),
+    /// grow the bucket vector once the average chain length, entries
+    /// divided by buckets, would exceed load_factor
+    pub fn with_max_load_factor(mut self, load_factor: f32) -> ChainedHashBuilder<K, U, S> {
+        assert!(load_factor > 0.0, "max load factor must be positive");
+        self.max_load_factor = Some(load_factor);
+        self
+    }
+
+    /// factor the bucket vector is multiplied by when growing. Must be
+    /// greater than 1
+    pub fn with_growth_factor(mut self, growth_factor: f32) -> ChainedHashBuilder<K, U, S> {
+        assert!(growth_factor > 1.0, "growth factor must be greater than 1");
+        self.growth_factor = growth_factor;
+        self
+    }
+
+    /// hash keys with hash_builder rather than a randomly keyed SipHash.
+    /// Any BuildHasher will do, as will the hashers in hash_table::hashers
+    pub fn with_hasher<T>(self, hash_builder: T) -> ChainedHashBuilder<K, U, T> {
+        ChainedHashBuilder::<K, U, T> {
+            _phantom_k: PhantomData {},
+            _phantom_u: PhantomData {},
+            capacity: self.capacity,
+            max_load_factor: self.max_load_factor,
+            growth_factor: self.growth_factor,
+            hash_builder,
+        }
+    }
+
+    pub fn build(self) -> ChainedHash<K, U, S> {
+        let mut hash = ChainedHash::<K, U, S> {
+            table: Vec::with_capacity(self.capacity),
             capacity: self.capacity,
+            len: 0,
+            max_load_factor: self.max_load_factor,
+            growth_factor: self.growth_factor,
+            hash_builder: self.hash_builder,
         };
 
         // initialize the hash table
@@ -94,27 +189,23 @@ impl<U: Default + std::cmp::PartialEq> ChainedHashBuilder<U> {
     }
 }
 
-impl<U: std::marker::Copy + std::fmt::Debug + std::cmp::PartialEq> HashTable<U> for ChainedHash<U> {
-    fn insert(&mut self, key: u16, data: U) -> Result<(), HashTableError> {
-        let x: usize = self.hash(key).into();
-
-        let data = HashTableEntry::<U> {
-            key: key,
-            data: Some(Box::new(data)),
-        };
-
-        let entry = ChainedHashEntry::<U> {
-            data: data,
-            next: mem::replace(&mut self.table[x].head, None),
-        };
-        self.table[x].head = Some(Box::new(entry));
-        Ok(())
+impl<K: Hash + Eq, U, S: HashFn<K>> HashTable<K, U> for ChainedHash<K, U, S> {
+    fn insert(&mut self, key: K, data: U) -> Result<Option<U>, HashTableError> {
+        match self.entry(key) {
+            Entry::Occupied(mut entry) => Ok(Some(entry.insert(data))),
+            Entry::Vacant(entry) => {
+                entry.insert(data);
+                Ok(None)
+            }
+        }
     }
 
-    fn delete(&mut self, key: u16) -> Result<(), HashTableError> {
-
-        let x: usize = self.hash(key).into();
-        if self.table[x] == (List { head: None }) {
+    fn remove(&mut self, key: &K) -> Result<U, HashTableError> {
+        if self.get_capacity() == 0 {
+            return Err(HashTableError::NotFound);
+        }
+        let x = self.hash(key);
+        if self.table[x].head.is_none() {
             return Err(HashTableError::NotFound);
         }
 
@@ -124,9 +215,11 @@ impl<U: std::marker::Copy + std::fmt::Debug + std::cmp::PartialEq> HashTable<U>
         loop {
             match current {
                 None => break,
-                Some(node) if node.data.key == key => {
-                    *current = node.next.take();
-                    return Ok(());
+                Some(node) if node.data.key == *key => {
+                    let next = node.next.take();
+                    let node = mem::replace(current, next).unwrap();
+                    self.len -= 1;
+                    return Ok(*node.data.data.unwrap());
                 }
                 Some(node) => {
                     current = &mut node.next;
@@ -136,45 +229,88 @@ impl<U: std::marker::Copy + std::fmt::Debug + std::cmp::PartialEq> HashTable<U>
         Err(HashTableError::NotFound)
     }
 
-    fn lookup(&self, key: u16) -> Result<U, HashTableError>
-    where
-        U: Copy,
-    {
-        let x: usize = self.hash(key).into();
+    fn get(&self, key: &K) -> Option<&U> {
+        if self.get_capacity() == 0 {
+            return None;
+        }
+        let x = self.hash(key);
 
-        if self.table[x] == (List { head: None }) {
-            return Err(HashTableError::NotFound);
+        for value in self.table[x].iter().flatten() {
+            if value.data.key == *key {
+                return value.data.data.as_deref();
+            }
         }
 
-        for elem in self.table[x].iter() {
-            match elem {
-                Some(value) => {
-                    if value.data.key == key {
-                        return Ok(**(value.data.data.as_ref()).unwrap());
-                    }
-                }
-                _ => {}
+        None
+    }
+
+    fn get_mut(&mut self, key: &K) -> Option<&mut U> {
+        if self.get_capacity() == 0 {
+            return None;
+        }
+        let x = self.hash(key);
+
+        let mut current = &mut self.table[x].head;
+        while let Some(node) = current {
+            if node.data.key == *key {
+                return node.data.data.as_deref_mut();
             }
+            current = &mut node.next;
         }
 
-        return Err(HashTableError::NotFound);
+        None
+    }
+
+    fn len(&self) -> usize {
+        self.len
+    }
+
+    fn clear(&mut self) {
+        self.drain();
+    }
+
+    fn capacity(&self) -> usize {
+        self.get_capacity()
     }
 }
 
-//impl<T, U, const N: T> HashFn<T> for ChainedHash<T, U, N> where T: std::ops::Rem<usize, Output = usize> {
-impl<U: std::cmp::PartialEq> HashFn for ChainedHash<U> {
-    fn hash(&self, key: u16) -> u16 {
-        key % self.get_capacity()
+impl<K, U, S: HashFn<K>> ChainedHash<K, U, S> {
+    /// the bucket key is chained in
+    fn hash(&self, key: &K) -> usize {
+        (self.hash_builder.hash(key) % self.get_capacity() as u64) as usize
     }
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
+    use hash_table::testing::{check_table, TableBuilder};
+
+    struct Builder;
+
+    impl TableBuilder for Builder {
+        type Table<K: Hash + Eq + Default, U, S: HashFn<K>> = ChainedHash<K, U, S>;
+
+        fn build<K: Hash + Eq + Default, U, S: HashFn<K>>(
+            &self,
+            hash_builder: S,
+        ) -> ChainedHash<K, U, S> {
+            ChainedHashBuilder::new()
+                .with_capacity(8)
+                .with_max_load_factor(1.0)
+                .with_hasher(hash_builder)
+                .build()
+        }
+    }
+
+    #[test]
+    fn passes_table_tests() {
+        check_table(Builder);
+    }
 
     #[test]
     fn can_create_basic_hash() {
-        let mut x = ChainedHashBuilder::<u16>::new().with_capacity(3).build();
+        let mut x = ChainedHashBuilder::<u16, u16>::new().with_capacity(3).build();
         assert_eq!(x.get_capacity(), 3);
         let mut item = 10;
         assert!(x.insert(1, item).is_ok());
@@ -183,12 +319,12 @@ mod tests {
         item += 10;
         assert!(x.insert(3, item).is_ok());
         item += 10;
-        assert_eq!(x.insert(4, item).is_ok(), true);
+        assert!(x.insert(4, item).is_ok());
     }
 
     #[test]
     fn can_create_basic_hash_and_search() {
-        let mut x = ChainedHashBuilder::<u16>::new().with_capacity(3).build();
+        let mut x = ChainedHashBuilder::<u16, u16>::new().with_capacity(3).build();
         assert_eq!(x.get_capacity(), 3);
         let mut item = 10;
         assert!(x.insert(1, item).is_ok());
@@ -203,31 +339,31 @@ mod tests {
         item += 10;
         assert!(x.insert(6, item).is_ok());
 
-        let ret = x.lookup(1);
+        let ret = x.lookup(&1);
         assert!(ret.is_ok());
         assert_eq!(ret.unwrap(), 10);
-        let ret = x.lookup(2);
+        let ret = x.lookup(&2);
         assert!(ret.is_ok());
         assert_eq!(ret.unwrap(), 20);
-        let ret = x.lookup(3);
+        let ret = x.lookup(&3);
         assert!(ret.is_ok());
         assert_eq!(ret.unwrap(), 30);
-        let ret = x.lookup(4);
+        let ret = x.lookup(&4);
         assert!(ret.is_ok());
         assert_eq!(ret.unwrap(), 40);
-        let ret = x.lookup(5);
+        let ret = x.lookup(&5);
         assert!(ret.is_ok());
         assert_eq!(ret.unwrap(), 50);
-        let ret = x.lookup(6);
+        let ret = x.lookup(&6);
         assert!(ret.is_ok());
         assert_eq!(ret.unwrap(), 60);
-        let ret = x.lookup(17);
-        assert_eq!(ret.is_ok(), false);
+        let ret = x.lookup(&17);
+        assert!(ret.is_err());
     }
 
     #[test]
     fn can_create_basic_hash_and_delete() {
-        let mut x = ChainedHashBuilder::<u16>::new().with_capacity(3).build();
+        let mut x = ChainedHashBuilder::<u16, u16>::new().with_capacity(3).build();
         assert_eq!(x.get_capacity(), 3);
         let mut item = 10;
         assert!(x.insert(1, item).is_ok());
@@ -242,49 +378,49 @@ mod tests {
         item += 10;
         assert!(x.insert(6, item).is_ok());
 
-        let ret = x.lookup(1);
+        let ret = x.lookup(&1);
         assert!(ret.is_ok());
         assert_eq!(ret.unwrap(), 10);
-        let ret = x.lookup(2);
+        let ret = x.lookup(&2);
         assert!(ret.is_ok());
         assert_eq!(ret.unwrap(), 20);
-        let ret = x.lookup(3);
+        let ret = x.lookup(&3);
         assert!(ret.is_ok());
         assert_eq!(ret.unwrap(), 30);
-        let ret = x.lookup(4);
+        let ret = x.lookup(&4);
         assert!(ret.is_ok());
         assert_eq!(ret.unwrap(), 40);
-        let ret = x.lookup(5);
+        let ret = x.lookup(&5);
         assert!(ret.is_ok());
         assert_eq!(ret.unwrap(), 50);
-        let ret = x.lookup(6);
+        let ret = x.lookup(&6);
         assert!(ret.is_ok());
         assert_eq!(ret.unwrap(), 60);
 
-        let ret = x.delete(1);
+        let ret = x.delete(&1);
         assert!(ret.is_ok());
-        let ret = x.lookup(1);
-        assert_eq!(ret.is_ok(), false);
-        let ret = x.delete(2);
+        let ret = x.lookup(&1);
+        assert!(ret.is_err());
+        let ret = x.delete(&2);
         assert!(ret.is_ok());
-        let ret = x.lookup(2);
-        assert_eq!(ret.is_ok(), false);
-        let ret = x.delete(3);
+        let ret = x.lookup(&2);
+        assert!(ret.is_err());
+        let ret = x.delete(&3);
         assert!(ret.is_ok());
-        let ret = x.lookup(3);
-        assert_eq!(ret.is_ok(), false);
-        let ret = x.delete(4);
+        let ret = x.lookup(&3);
+        assert!(ret.is_err());
+        let ret = x.delete(&4);
         assert!(ret.is_ok());
-        let ret = x.lookup(4);
-        assert_eq!(ret.is_ok(), false);
-        let ret = x.delete(5);
+        let ret = x.lookup(&4);
+        assert!(ret.is_err());
+        let ret = x.delete(&5);
         assert!(ret.is_ok());
-        let ret = x.lookup(5);
-        assert_eq!(ret.is_ok(), false);
-        let ret = x.delete(6);
+        let ret = x.lookup(&5);
+        assert!(ret.is_err());
+        let ret = x.delete(&6);
         assert!(ret.is_ok());
-        let ret = x.lookup(6);
-        assert_eq!(ret.is_ok(), false);
+        let ret = x.lookup(&6);
+        assert!(ret.is_err());
 
         // see if we can insert them again
 
@@ -295,27 +431,148 @@ mod tests {
         item += 100;
         assert!(x.insert(3, item).is_ok());
 
-        let ret = x.lookup(1);
+        let ret = x.lookup(&1);
         assert!(ret.is_ok());
         assert_eq!(ret.unwrap(), 100);
-        let ret = x.lookup(2);
+        let ret = x.lookup(&2);
         assert!(ret.is_ok());
         assert_eq!(ret.unwrap(), 200);
-        let ret = x.lookup(3);
+        let ret = x.lookup(&3);
         assert!(ret.is_ok());
         assert_eq!(ret.unwrap(), 300);
 
-        let ret = x.delete(1);
+        let ret = x.delete(&1);
         assert!(ret.is_ok());
-        let ret = x.lookup(1);
-        assert_eq!(ret.is_ok(), false);
-        let ret = x.delete(2);
+        let ret = x.lookup(&1);
+        assert!(ret.is_err());
+        let ret = x.delete(&2);
         assert!(ret.is_ok());
-        let ret = x.lookup(2);
-        assert_eq!(ret.is_ok(), false);
-        let ret = x.delete(3);
+        let ret = x.lookup(&2);
+        assert!(ret.is_err());
+        let ret = x.delete(&3);
         assert!(ret.is_ok());
-        let ret = x.lookup(3);
-        assert_eq!(ret.is_ok(), false);
+        let ret = x.lookup(&3);
+        assert!(ret.is_err());
+    }
+
+    #[test]
+    fn can_grow_chained_hash() {
+        let mut x = ChainedHashBuilder::<u16, u16>::new()
+            .with_capacity(2)
+            .with_max_load_factor(2.0)
+            .build();
+        for key in 0..100 {
+            assert!(x.insert(key, key * 10).is_ok());
+            assert!(x.len as f32 <= x.get_capacity() as f32 * 2.0);
+        }
+        assert_eq!(x.get_capacity(), 64);
+        assert_eq!(x.table.len(), 64);
+        for key in 0..100 {
+            let ret = x.lookup(&key);
+            assert!(ret.is_ok());
+            assert_eq!(ret.unwrap(), key * 10);
+        }
+        for key in 0..50 {
+            assert!(x.delete(&key).is_ok());
+        }
+        assert_eq!(x.len, 50);
+        for key in 0..100 {
+            assert_eq!(x.lookup(&key).is_ok(), key >= 50);
+        }
+    }
+
+    #[test]
+    fn can_insert_into_empty_chained_hash() {
+        let mut x = ChainedHashBuilder::<u16, u16>::new().build();
+        assert_eq!(x.get_capacity(), 0);
+        assert!(x.lookup(&1).is_err());
+        assert!(x.insert(1, 10).is_ok());
+        assert!(x.insert(2, 20).is_ok());
+        assert_eq!(x.get_capacity(), 1);
+        assert_eq!(x.lookup(&1).unwrap(), 10);
+        assert_eq!(x.lookup(&2).unwrap(), 20);
+
+        let mut x = ChainedHashBuilder::<u16, u16>::new()
+            .with_max_load_factor(1.0)
+            .build();
+        for key in 0..10 {
+            assert!(x.insert(key, key).is_ok());
+        }
+        assert!(x.get_capacity() >= 10);
+    }
+
+    #[test]
+    fn updating_existing_key_does_not_resize() {
+        let mut x = ChainedHashBuilder::<u16, u16>::new()
+            .with_capacity(4)
+            .with_max_load_factor(0.5)
+            .build();
+        assert!(x.insert(1, 10).is_ok());
+        assert!(x.insert(2, 20).is_ok());
+        // the table is at its maximum load, one more key would grow it
+        for data in 11..20 {
+            assert_eq!(x.insert(1, data).unwrap(), Some(data - 1));
+        }
+        assert_eq!(x.get_capacity(), 4);
+        assert!(x.insert(3, 30).is_ok());
+        assert!(x.get_capacity() > 4);
+    }
+
+    #[test]
+    fn entries_never_run_out_of_room() {
+        // chains have no limit, so every key has an entry
+        let mut x = ChainedHashBuilder::<u16, u16>::new().with_capacity(3).build();
+        for key in 0..10 {
+            *x.entry(key).or_default() += key;
+        }
+        assert_eq!(x.get_capacity(), 3);
+        assert_eq!(x.len(), 10);
+        for key in 0..10 {
+            assert_eq!(x.get(&key), Some(&key));
+        }
+    }
+
+    #[test]
+    fn can_iterate_chained_hash() {
+        let mut x = ChainedHashBuilder::<u16, u16>::new().with_capacity(2).build();
+        for key in 0..6 {
+            assert!(x.insert(key, key * 10).is_ok());
+        }
+        assert!(x.delete(&3).is_ok());
+
+        let mut entries: Vec<(u16, u16)> = x.iter().map(|(k, v)| (*k, *v)).collect();
+        entries.sort();
+        assert_eq!(entries, vec![(0, 0), (1, 10), (2, 20), (4, 40), (5, 50)]);
+        let mut keys: Vec<u16> = x.keys().copied().collect();
+        keys.sort();
+        assert_eq!(keys, vec![0, 1, 2, 4, 5]);
+        assert_eq!(x.values().sum::<u16>(), 120);
+
+        for (key, data) in x.iter_mut() {
+            *data += key;
+        }
+        for (_, data) in &mut x {
+            *data += 1;
+        }
+        assert_eq!((&x).into_iter().count(), 5);
+        assert_eq!(x.get(&5), Some(&56));
+
+        let mut drained: Vec<(u16, u16)> = x.drain().collect();
+        drained.sort();
+        assert_eq!(drained, vec![(0, 1), (1, 12), (2, 23), (4, 45), (5, 56)]);
+        assert_eq!(x.iter().count(), 0);
+        assert_eq!(x.len, 0);
+        assert_eq!(x.get_capacity(), 2);
+        assert!(x.get(&5).is_none());
+
+        // the table is usable after a drain, even a partial one
+        assert!(x.insert(7, 70).is_ok());
+        assert!(x.insert(8, 80).is_ok());
+        assert_eq!(x.drain().take(1).count(), 1);
+        assert!(x.get(&7).is_none() && x.get(&8).is_none());
+        assert!(x.insert(9, 90).is_ok());
+
+        let owned: Vec<(u16, u16)> = x.into_iter().collect();
+        assert_eq!(owned, vec![(9, 90)]);
     }
 }
@@ -0,0 +1,149 @@
+use criterion::{criterion_group, criterion_main, BenchmarkId, Criterion};
+use cuckoo_hash::{CuckooHashBuilder, Eviction, StashOverflow};
+use hash_table::hashers::XxBuildHasher;
+use hash_table::HashTable;
+
+const BUCKETS: usize = 1024;
+
+// fill a table of BUCKETS buckets per table to the given fraction of its
+// slots, outside the stash
+fn fill(builder: CuckooHashBuilder<u64, u64>, d: usize, bucket_size: usize, load: f64) -> usize {
+    let mut x = builder
+        .with_hash_capacity(BUCKETS)
+        .with_hash_functions(d)
+        .with_bucket_size(bucket_size)
+        .with_grow_after_failed_rehashes(4)
+        .with_seed(1)
+        .with_hasher(XxBuildHasher::with_seed(1))
+        .build();
+    let keys = (d * BUCKETS * bucket_size) as f64 * load;
+    for key in 0..keys as u64 {
+        x.insert(key, key).unwrap();
+    }
+    x.get_rehash_count()
+}
+
+fn eviction(c: &mut Criterion) {
+    let mut group = c.benchmark_group("eviction");
+    // load each layout close to what two tables of single slots, two of
+    // four slots and three of single slots can sustain
+    let layouts = [(2, 1, 0.45), (2, 4, 0.9), (3, 1, 0.85)];
+    let policies = [
+        ("greedy", Eviction::Greedy),
+        ("random_walk", Eviction::RandomWalk),
+        ("bfs", Eviction::BreadthFirst),
+    ];
+    for (d, bucket_size, load) in layouts {
+        for (name, eviction) in policies {
+            let id = format!("d{}_b{}_{}", d, bucket_size, load);
+            group.bench_with_input(BenchmarkId::new(name, id), &eviction, |b, eviction| {
+                b.iter(|| {
+                    let builder = CuckooHashBuilder::new().with_eviction(*eviction);
+                    fill(builder, d, bucket_size, load)
+                })
+            });
+        }
+    }
+    group.finish();
+}
+
+fn max_displacements(c: &mut Criterion) {
+    let mut group = c.benchmark_group("max_displacements");
+    for displacements in [2, 8, 16, 64, 256] {
+        group.bench_with_input(
+            BenchmarkId::from_parameter(displacements),
+            &displacements,
+            |b, displacements| {
+                b.iter(|| {
+                    let builder = CuckooHashBuilder::new().with_max_displacements(*displacements);
+                    fill(builder, 2, 1, 0.45)
+                })
+            },
+        );
+    }
+    group.finish();
+}
+
+fn stash_capacity(c: &mut Criterion) {
+    let mut group = c.benchmark_group("stash_capacity");
+    for capacity in [0, 2, 8, 32] {
+        group.bench_with_input(
+            BenchmarkId::from_parameter(capacity),
+            &capacity,
+            |b, capacity| {
+                b.iter(|| {
+                    let builder = CuckooHashBuilder::new().with_stash_capacity(*capacity);
+                    fill(builder, 2, 1, 0.45)
+                })
+            },
+        );
+    }
+    group.finish();
+}
+
+fn stash_overflow(c: &mut Criterion) {
+    // grow from a handful of buckets, so the policy decides how often
+    // the table is rehashed on the way
+    let mut group = c.benchmark_group("stash_overflow");
+    let policies = [
+        ("rehash", StashOverflow::Rehash),
+        ("grow", StashOverflow::Grow),
+        ("error", StashOverflow::Error),
+    ];
+    for (name, overflow) in policies {
+        group.bench_with_input(
+            BenchmarkId::from_parameter(name),
+            &overflow,
+            |b, overflow| {
+                b.iter(|| {
+                    let mut x = CuckooHashBuilder::<u64, u64>::new()
+                        .with_hash_capacity(4)
+                        .with_stash_overflow(*overflow)
+                        .with_grow_after_failed_rehashes(4)
+                        .with_seed(1)
+                        .with_hasher(XxBuildHasher::with_seed(1))
+                        .build();
+                    let mut inserted = 0;
+                    for key in 0..4096 {
+                        inserted += x.insert(key, key).is_ok() as usize;
+                    }
+                    inserted
+                })
+            },
+        );
+    }
+    group.finish();
+}
+
+fn lookup(c: &mut Criterion) {
+    let mut group = c.benchmark_group("lookup");
+    for d in [2, 3, 4] {
+        let mut x = CuckooHashBuilder::<u64, u64>::new()
+            .with_hash_capacity(BUCKETS)
+            .with_hash_functions(d)
+            .with_seed(1)
+            .with_hasher(XxBuildHasher::with_seed(1))
+            .build();
+        let keys = (d * BUCKETS * 45 / 100) as u64;
+        for key in 0..keys {
+            x.insert(key, key).unwrap();
+        }
+        group.bench_with_input(BenchmarkId::new("hits", d), &x, |b, x| {
+            b.iter(|| (0..keys).filter_map(|key| x.get(&key)).count())
+        });
+        group.bench_with_input(BenchmarkId::new("misses", d), &x, |b, x| {
+            b.iter(|| (keys..2 * keys).filter_map(|key| x.get(&key)).count())
+        });
+    }
+    group.finish();
+}
+
+criterion_group!(
+    benches,
+    eviction,
+    max_displacements,
+    stash_capacity,
+    stash_overflow,
+    lookup
+);
+criterion_main!(benches);
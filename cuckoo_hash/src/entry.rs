@@ -0,0 +1,97 @@
+use crate::{CuckooHash, Slot};
+use hash_table::entry::{EntryTable, Occupied, Vacant};
+use hash_table::hashers::SipBuildHasher;
+use hash_table::{HashFn, HashTableError};
+use std::hash::Hash;
+
+/// a view into a CuckooHash for a single key, which is either occupied
+/// by the table or stash slot holding the key, or vacant
+pub type Entry<'a, K, U, S = SipBuildHasher> =
+    hash_table::entry::Entry<OccupiedEntry<'a, K, U, S>, VacantEntry<'a, K, U, S>>;
+
+/// the slot holding the requested key
+pub struct OccupiedEntry<'a, K, U, S = SipBuildHasher> {
+    pub(crate) table: &'a mut CuckooHash<K, U, S>,
+    pub(crate) slot: Slot,
+}
+
+/// the slot left vacant for the requested key, after any displacement
+/// or rehash it took to free one
+pub struct VacantEntry<'a, K, U, S = SipBuildHasher> {
+    pub(crate) table: &'a mut CuckooHash<K, U, S>,
+    pub(crate) key: K,
+    pub(crate) slot: Slot,
+}
+
+impl<'a, K: Hash + Eq + Default, U, S: HashFn<K>> Occupied<'a> for OccupiedEntry<'a, K, U, S> {
+    type Key = K;
+    type Data = U;
+
+    fn key(&self) -> &K {
+        &self.table.entry_at(self.slot).key
+    }
+
+    fn get(&self) -> &U {
+        self.table.entry_at(self.slot).data.as_deref().unwrap()
+    }
+
+    fn get_mut(&mut self) -> &mut U {
+        self.table
+            .entry_at_mut(self.slot)
+            .data
+            .as_deref_mut()
+            .unwrap()
+    }
+
+    fn into_mut(self) -> &'a mut U {
+        self.table
+            .entry_at_mut(self.slot)
+            .data
+            .as_deref_mut()
+            .unwrap()
+    }
+
+    /// clear the slot, returning its data
+    fn remove(self) -> U {
+        *self.table.take(self.slot)
+    }
+}
+
+impl<'a, K: Hash + Eq + Default, U, S: HashFn<K>> Vacant<'a> for VacantEntry<'a, K, U, S> {
+    type Key = K;
+    type Data = U;
+
+    fn key(&self) -> &K {
+        &self.key
+    }
+
+    fn into_key(self) -> K {
+        self.key
+    }
+
+    /// place the key and data in the slot entry freed for them
+    fn insert(self, data: U) -> &'a mut U {
+        if let Slot::Stash(_) = self.slot {
+            self.table.stash_len += 1;
+        }
+        self.table.len += 1;
+        let entry = self.table.entry_at_mut(self.slot);
+        entry.key = self.key;
+        entry.data.insert(Box::new(data))
+    }
+}
+
+impl<K: Hash + Eq + Default, U, S: HashFn<K>> EntryTable<K, U> for CuckooHash<K, U, S> {
+    type Occupied<'a>
+        = OccupiedEntry<'a, K, U, S>
+    where
+        Self: 'a;
+    type Vacant<'a>
+        = VacantEntry<'a, K, U, S>
+    where
+        Self: 'a;
+
+    fn entry(&mut self, key: K) -> Result<Entry<'_, K, U, S>, HashTableError> {
+        CuckooHash::entry(self, key)
+    }
+}
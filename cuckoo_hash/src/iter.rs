@@ -0,0 +1,104 @@
+use crate::CuckooHash;
+use hash_table::{iter, slots, HashFn, HashTableEntry};
+use std::hash::Hash;
+use std::iter::{Chain, Flatten};
+use std::mem;
+
+type Slots<'a, K, U> = Chain<
+    Flatten<std::slice::Iter<'a, Vec<HashTableEntry<K, U>>>>,
+    std::slice::Iter<'a, HashTableEntry<K, U>>,
+>;
+
+type SlotsMut<'a, K, U> = Chain<
+    Flatten<std::slice::IterMut<'a, Vec<HashTableEntry<K, U>>>>,
+    std::slice::IterMut<'a, HashTableEntry<K, U>>,
+>;
+
+type IntoSlots<K, U> = Chain<
+    Flatten<std::vec::IntoIter<Vec<HashTableEntry<K, U>>>>,
+    std::vec::IntoIter<HashTableEntry<K, U>>,
+>;
+
+/// iterator over the key and data of every entry in a CuckooHash,
+/// covering each table in turn, then the stash
+pub type Iter<'a, K, U> = slots::Iter<'a, K, U, Slots<'a, K, U>>;
+
+/// iterator over the key and mutable data of every entry in a
+/// CuckooHash
+pub type IterMut<'a, K, U> = slots::IterMut<'a, K, U, SlotsMut<'a, K, U>>;
+
+pub type Keys<'a, K, U> = iter::Keys<Iter<'a, K, U>>;
+
+pub type Values<'a, K, U> = iter::Values<Iter<'a, K, U>>;
+
+/// owning iterator over the entries of a CuckooHash
+pub type IntoIter<K, U> = slots::IntoIter<K, U, IntoSlots<K, U>>;
+
+/// iterator over the entries removed from a CuckooHash by drain. The
+/// table is empty as soon as drain returns, whether or not this is
+/// run to completion
+pub type Drain<'a, K, U> = iter::Drain<'a, IntoIter<K, U>>;
+
+impl<K, U, S> CuckooHash<K, U, S> {
+    pub fn iter(&self) -> Iter<'_, K, U> {
+        Iter::new(self.tables.iter().flatten().chain(self.stash.iter()))
+    }
+
+    pub fn iter_mut(&mut self) -> IterMut<'_, K, U> {
+        IterMut::new(
+            self.tables
+                .iter_mut()
+                .flatten()
+                .chain(self.stash.iter_mut()),
+        )
+    }
+
+    pub fn keys(&self) -> Keys<'_, K, U> {
+        Keys::new(self.iter())
+    }
+
+    pub fn values(&self) -> Values<'_, K, U> {
+        Values::new(self.iter())
+    }
+}
+
+impl<K: Hash + Eq + Default, U, S: HashFn<K>> CuckooHash<K, U, S> {
+    /// remove every entry, keeping the capacity of the tables and stash
+    pub fn drain(&mut self) -> Drain<'_, K, U> {
+        let empty = (0..self.tables.len()).map(|_| Vec::new()).collect();
+        let tables = mem::replace(&mut self.tables, empty);
+        let stash = mem::take(&mut self.stash);
+        self.initialize_hash_tables();
+        self.initialize_stash();
+        self.len = 0;
+        self.stash_len = 0;
+        Drain::new(IntoIter::new(tables.into_iter().flatten().chain(stash)))
+    }
+}
+
+impl<K, U, S> IntoIterator for CuckooHash<K, U, S> {
+    type Item = (K, U);
+    type IntoIter = IntoIter<K, U>;
+
+    fn into_iter(self) -> IntoIter<K, U> {
+        IntoIter::new(self.tables.into_iter().flatten().chain(self.stash))
+    }
+}
+
+impl<'a, K, U, S> IntoIterator for &'a CuckooHash<K, U, S> {
+    type Item = (&'a K, &'a U);
+    type IntoIter = Iter<'a, K, U>;
+
+    fn into_iter(self) -> Iter<'a, K, U> {
+        self.iter()
+    }
+}
+
+impl<'a, K, U, S> IntoIterator for &'a mut CuckooHash<K, U, S> {
+    type Item = (&'a K, &'a mut U);
+    type IntoIter = IterMut<'a, K, U>;
+
+    fn into_iter(self) -> IterMut<'a, K, U> {
+        self.iter_mut()
+    }
+}
@@ -1,329 +1,1092 @@
 #![allow(dead_code)]
 
+use hash_table::hashers::{SipBuildHasher, Tabulation};
 use hash_table::{HashFn, HashTable, HashTableEntry, HashTableError};
-use rand::Rng;
+use rand::rngs::StdRng;
+use rand::{Rng, RngCore, SeedableRng};
+use std::collections::HashSet;
+use std::hash::Hash;
 use std::marker::PhantomData;
+use std::ops::Range;
 
 use std::mem;
 
-const DEFAULT_STASH_SIZE: u16 = 8;
+mod entry;
+mod iter;
+
+pub use entry::{Entry, OccupiedEntry, VacantEntry};
+pub use hash_table::entry::{Occupied, Vacant};
+pub use iter::{Drain, IntoIter, Iter, IterMut, Keys, Values};
+
+const DEFAULT_STASH_SIZE: usize = 8;
 const MAX_SECONDARY_HASH_ITERATIONS: u16 = 8;
+const DEFAULT_MAX_REHASH_ATTEMPTS: usize = 16;
+const DEFAULT_GROWTH_FACTOR: f32 = 2.0;
+const DEFAULT_BUCKET_SIZE: usize = 1;
+const DEFAULT_HASH_FUNCTIONS: usize = 2;
+const MAX_BFS_SLOTS: usize = 512;
 
-struct CuckooHash<U>
-where
-    U: Copy,
-{
-    primary: Vec<HashTableEntry<U>>,
-    secondary: Vec<HashTableEntry<U>>,
-    stash: Vec<HashTableEntry<U>>,
-    // capacity of primary and secondary
-    hash_capacity: u16,
+/// where an entry lives in a CuckooHash, a slot of one of the tables or
+/// of the stash
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub(crate) enum Slot {
+    Table(usize, usize),
+    Stash(usize),
+}
+
+/// how an insert finds room for a key whose buckets are all full
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Eviction {
+    /// displace the entry of the key's bucket in the first table, and
+    /// carry that on to its bucket in the next table, and so on round
+    /// the tables, as classic cuckoo hashing does
+    Greedy,
+    /// displace the entry of a random full bucket, and carry that on to
+    /// one of its other buckets, until an entry lands in a vacant slot
+    RandomWalk,
+    /// search breadth first for the shortest chain of displacements
+    /// ending in a vacant slot, and only then move entries along it. An
+    /// insert that finds no room has moved nothing
+    BreadthFirst,
+}
+
+/// what an insert does when eviction finds no room and the stash is full
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum StashOverflow {
+    /// rehash every entry under new hash functions, growing the tables
+    /// only after grow_after_failed_rehashes failed attempts
+    Rehash,
+    /// rehash every entry into grown tables straight away
+    Grow,
+    /// fail the insert with TableFull, leaving the table as it was
+    Error,
+}
+
+pub struct CuckooHash<K, U, S = SipBuildHasher> {
+    // one table per hash function, each split into buckets of
+    // bucket_size entries. A key hashing to a bucket can be held in any
+    // of its slots
+    tables: Vec<Vec<HashTableEntry<K, U>>>,
+    stash: Vec<HashTableEntry<K, U>>,
+    // number of buckets in each table
+    hash_capacity: usize,
+    // slots per bucket
+    bucket_size: usize,
     // capacity of stash
-    stash_capacity: u16,
-    // random number for hash of primary
-    primary_random: u16,
-    // random number for hash of secondary
-    secondary_random: u16,
+    stash_capacity: usize,
+    // number of entries across the tables and stash
+    len: usize,
+    // number of entries in the stash
+    stash_len: usize,
+    // random numbers seeding the hash function of each table
+    randoms: Vec<u64>,
+    // simple tabulation hash functions drawn with the random numbers
+    // above. Drawn independently, a key's slots in the tables are
+    // independent of each other, and tabulation is strong enough that
+    // cuckoo hashing behaves as with truly random functions
+    hash_fns: Vec<Tabulation>,
+    // how inserts and rehashes make room for a key
+    eviction: Eviction,
+    // displacements an insert makes, or for breadth-first eviction the
+    // longest displacement path it searches for, before using the stash
+    max_displacements: usize,
+    // what an insert does when neither eviction nor the stash find room
+    stash_overflow: StashOverflow,
+    // number of times new hash functions have been drawn for a rehash
+    rehashes: usize,
+    // new hash functions a rehash draws before giving up
+    max_rehash_attempts: usize,
+    // grow the tables once a rehash has failed this many times in a row,
+    // None disables growing
+    grow_after_failed_rehashes: Option<usize>,
+    // shrink once entries fall below this fraction of the slots in the
+    // tables, None disables shrinking
+    min_load_factor: Option<f32>,
+    // hash_capacity is multiplied by this when growing, divided when
+    // shrinking
+    growth_factor: f32,
+    // source of the random numbers. Seeded, it makes the placement of
+    // every entry and every rehash replayable
+    rng: StdRng,
+    // hashes keys to the 64-bit value the hash functions are applied to
+    hash_builder: S,
 }
 
-impl<U: std::marker::Copy> CuckooHash<U> {
-    fn get_hash_capacity(&self) -> u16 {
+impl<K, U, S> CuckooHash<K, U, S> {
+    fn get_hash_capacity(&self) -> usize {
         self.hash_capacity
     }
-    fn get_stash_capacity(&self) -> u16 {
+    fn get_stash_capacity(&self) -> usize {
         self.stash_capacity
     }
 
-    pub fn update_primary_random(&mut self) {
-        self.primary_random = rand::thread_rng().gen::<u16>();
+    /// number of hash functions, and so tables, a key can be placed with
+    pub fn get_hash_functions(&self) -> usize {
+        self.tables.len()
+    }
+
+    pub fn update_random(&mut self, table: usize) {
+        self.randoms[table] = self.rng.gen::<u64>();
+        self.hash_fns[table] = Tabulation::with_seed(self.randoms[table]);
+    }
+
+    pub fn get_random(&self, table: usize) -> u64 {
+        self.randoms[table]
+    }
+
+    pub fn get_rehash_count(&self) -> usize {
+        self.rehashes
     }
 
-    pub fn update_secondary_random(&mut self) {
-        self.secondary_random = rand::thread_rng().gen::<u16>();
+    // keys are hashed once to 64 bits, and the index into each table
+    // derived from that with the table's own hash function
+    fn index(&self, table: usize, hash: u64) -> usize {
+        self.reduce(self.hash_fns[table].hash_u64(hash))
     }
 
-    pub fn get_primary_random(&self) -> u16 {
-        self.primary_random
+    fn reduce(&self, hash: u64) -> usize {
+        reduce(hash, self.get_hash_capacity())
     }
 
-    pub fn get_secondary_random(&self) -> u16 {
-        self.secondary_random
+    /// the slots of a bucket in any of the tables
+    fn bucket(&self, bucket: usize) -> Range<usize> {
+        bucket * self.bucket_size..(bucket + 1) * self.bucket_size
     }
+
+    pub(crate) fn entry_at(&self, slot: Slot) -> &HashTableEntry<K, U> {
+        match slot {
+            Slot::Table(t, x) => &self.tables[t][x],
+            Slot::Stash(x) => &self.stash[x],
+        }
+    }
+
+    pub(crate) fn entry_at_mut(&mut self, slot: Slot) -> &mut HashTableEntry<K, U> {
+        match slot {
+            Slot::Table(t, x) => &mut self.tables[t][x],
+            Slot::Stash(x) => &mut self.stash[x],
+        }
+    }
+}
+
+// scale a hash to a slot index, a multiply and shift rather than a
+// division
+fn reduce(hash: u64, capacity: usize) -> usize {
+    ((hash as u128 * capacity as u128) >> 64) as usize
 }
 
-struct CuckooHashBuilder<U> {
+// the slot of a full bucket whose entry is displaced. With more than one
+// slot a random one is picked, so repeated displacements from the same
+// bucket walk through different entries
+fn victim(rng: &mut StdRng, bucket: usize, bucket_size: usize) -> usize {
+    if bucket_size == 1 {
+        return bucket;
+    }
+    bucket * bucket_size + rng.gen_range(0..bucket_size)
+}
+
+// the table a displaced entry in turn displaces another from, picked at
+// random among all but the one it was just displaced from, so the walk
+// never moves it straight back. With two tables that alternates between
+// them as classic cuckoo hashing does
+fn next_table(rng: &mut StdRng, tables: usize, from: Option<usize>) -> usize {
+    match from {
+        None => rng.gen_range(0..tables),
+        Some(from) => {
+            let t = rng.gen_range(0..tables - 1);
+            if t >= from {
+                t + 1
+            } else {
+                t
+            }
+        }
+    }
+}
+
+// the table a carried entry displaces another from. Greedy eviction goes
+// round the tables in order, starting with the first
+fn displacing_table(
+    eviction: Eviction,
+    rng: &mut StdRng,
+    tables: usize,
+    from: Option<usize>,
+) -> usize {
+    match eviction {
+        Eviction::Greedy => from.map_or(0, |from| (from + 1) % tables),
+        _ => next_table(rng, tables, from),
+    }
+}
+
+// the tables in which a carried entry looks for a vacant slot before
+// displacing from the given one. Greedy eviction only looks in that one
+fn probed_tables(eviction: Eviction, tables: usize, displacing: usize) -> Range<usize> {
+    match eviction {
+        Eviction::Greedy => displacing..displacing + 1,
+        _ => 0..tables,
+    }
+}
+
+/// breadth-first search from the buckets hash maps to for the shortest
+/// chain of displacements ending in a vacant slot, as the (table, slot)
+/// pairs of the chain in order. occupant gives the hash of the entry in
+/// a slot, None if it is vacant. The search is bounded by the number of
+/// displacements on the path, and by MAX_BFS_SLOTS
+fn shortest_path<F>(
+    hash: u64,
+    hash_fns: &[Tabulation],
+    hash_capacity: usize,
+    bucket_size: usize,
+    max_displacements: usize,
+    occupant: F,
+) -> Option<Vec<(usize, usize)>>
+where
+    F: Fn(usize, usize) -> Option<u64>,
+{
+    if hash_capacity == 0 {
+        return None;
+    }
+    let d = hash_fns.len();
+    // every slot reached, with the node it is reached from and the
+    // number of slots on the path to it
+    let mut nodes: Vec<((usize, usize), Option<usize>, usize)> = Vec::new();
+    let mut seen = HashSet::new();
+    let mut head = 0;
+    // the hash of the entry being moved, the table it is moved out of
+    // and its node, starting with the key being placed
+    let mut current = (hash, None, None, 0);
+    loop {
+        let (hash, from, parent, depth) = current;
+        for t in (0..d).filter(|t| Some(*t) != from) {
+            let bucket = reduce(hash_fns[t].hash_u64(hash), hash_capacity);
+            for y in bucket * bucket_size..(bucket + 1) * bucket_size {
+                if !seen.insert((t, y)) {
+                    continue;
+                }
+                if nodes.len() == MAX_BFS_SLOTS {
+                    return None;
+                }
+                nodes.push(((t, y), parent, depth + 1));
+                if occupant(t, y).is_none() {
+                    let mut path = Vec::new();
+                    let mut node = Some(nodes.len() - 1);
+                    while let Some(n) = node {
+                        path.push(nodes[n].0);
+                        node = nodes[n].1;
+                    }
+                    path.reverse();
+                    return Some(path);
+                }
+            }
+        }
+        // the next slot whose entry can be displaced without the path
+        // growing too long
+        loop {
+            let &((t, y), _, depth) = nodes.get(head)?;
+            head += 1;
+            if depth <= max_displacements {
+                current = (occupant(t, y).unwrap(), Some(t), Some(head - 1), depth);
+                break;
+            }
+        }
+    }
+}
+
+/// where a rehash puts every entry, by index into the list of entries
+/// being rehashed, along with the hash functions and capacity it was
+/// planned for. Planned before any entry is moved, so a rehash that
+/// cannot place every entry leaves the table as it was
+struct RehashPlan {
+    hash_capacity: usize,
+    bucket_size: usize,
+    randoms: Vec<u64>,
+    hash_fns: Vec<Tabulation>,
+    tables: Vec<Vec<Option<usize>>>,
+    stash: Vec<usize>,
+}
+
+impl RehashPlan {
+    /// empty tables of hash_capacity buckets, one per random number,
+    /// under the hash functions drawn with those
+    fn new(randoms: Vec<u64>, hash_capacity: usize, bucket_size: usize) -> RehashPlan {
+        let d = randoms.len();
+        RehashPlan {
+            hash_capacity,
+            bucket_size,
+            hash_fns: randoms.iter().map(|r| Tabulation::with_seed(*r)).collect(),
+            randoms,
+            tables: vec![vec![None; hash_capacity * bucket_size]; d],
+            stash: Vec::new(),
+        }
+    }
+
+    /// cuckoo insert the entries with the given hashes, as the table
+    /// would with the same settings. False if some entry finds no room
+    fn place(
+        &mut self,
+        hashes: &[u64],
+        stash_capacity: usize,
+        eviction: Eviction,
+        max_displacements: usize,
+        rng: &mut StdRng,
+    ) -> bool {
+        for i in 0..hashes.len() {
+            let left = match eviction {
+                Eviction::BreadthFirst => self.shortest_path(i, hashes, max_displacements),
+                _ => self.walk(i, hashes, eviction, max_displacements, rng),
+            };
+            if let Some(x) = left {
+                if self.stash.len() == stash_capacity {
+                    return false;
+                }
+                self.stash.push(x);
+            }
+        }
+        true
+    }
+
+    /// place entry i by a greedy or random walk, returning the entry
+    /// left without a slot if the walk runs out of displacements
+    fn walk(
+        &mut self,
+        i: usize,
+        hashes: &[u64],
+        eviction: Eviction,
+        max_displacements: usize,
+        rng: &mut StdRng,
+    ) -> Option<usize> {
+        if self.hash_capacity == 0 {
+            return Some(i);
+        }
+        let d = self.tables.len();
+        let bucket_size = self.bucket_size;
+        let mut x = i;
+        let mut from = None;
+        for displacements in 0..=max_displacements {
+            let buckets: Vec<usize> = self
+                .hash_fns
+                .iter()
+                .map(|f| reduce(f.hash_u64(hashes[x]), self.hash_capacity))
+                .collect();
+            let t = displacing_table(eviction, rng, d, from);
+            // a vacant slot in one of the entry's buckets
+            let vacant = probed_tables(eviction, d, t).find_map(|t| {
+                (buckets[t] * bucket_size..(buckets[t] + 1) * bucket_size)
+                    .find(|y| self.tables[t][*y].is_none())
+                    .map(|y| (t, y))
+            });
+            if let Some((t, y)) = vacant {
+                self.tables[t][y] = Some(x);
+                return None;
+            }
+            if displacements == max_displacements {
+                break;
+            }
+            let y = victim(rng, buckets[t], bucket_size);
+            x = self.tables[t][y].replace(x).unwrap();
+            from = Some(t);
+        }
+        Some(x)
+    }
+
+    /// place entry i along the shortest displacement path, returning it
+    /// if there is none
+    fn shortest_path(
+        &mut self,
+        i: usize,
+        hashes: &[u64],
+        max_displacements: usize,
+    ) -> Option<usize> {
+        let path = shortest_path(
+            hashes[i],
+            &self.hash_fns,
+            self.hash_capacity,
+            self.bucket_size,
+            max_displacements,
+            |t, y| self.tables[t][y].map(|x| hashes[x]),
+        );
+        let Some(path) = path else { return Some(i) };
+        for w in path.windows(2).rev() {
+            let ((t, y), (t2, y2)) = (w[0], w[1]);
+            self.tables[t2][y2] = self.tables[t][y].take();
+        }
+        let (t, y) = path[0];
+        self.tables[t][y] = Some(i);
+        None
+    }
+}
+
+pub struct CuckooHashBuilder<K, U, S = SipBuildHasher> {
+    _phantom_k: PhantomData<K>,
     _phantom_u: PhantomData<U>,
-    hash_capacity: u16,
-    stash_capacity: u16,
-    primary_random: u16,
-    secondary_random: u16,
+    hash_capacity: usize,
+    hash_functions: usize,
+    bucket_size: usize,
+    stash_capacity: usize,
+    eviction: Eviction,
+    max_displacements: Option<usize>,
+    stash_overflow: StashOverflow,
+    max_rehash_attempts: usize,
+    grow_after_failed_rehashes: Option<usize>,
+    min_load_factor: Option<f32>,
+    growth_factor: f32,
+    rng: StdRng,
+    hash_builder: S,
 }
 
-impl<U: Default + std::marker::Copy> CuckooHashBuilder<U> {
-    fn new() -> CuckooHashBuilder<U> {
-        CuckooHashBuilder::<U> {
+impl<K: Default, U> CuckooHashBuilder<K, U> {
+    pub fn new() -> CuckooHashBuilder<K, U> {
+        CuckooHashBuilder::<K, U> {
+            _phantom_k: PhantomData {},
             _phantom_u: PhantomData {},
             hash_capacity: 0,
+            hash_functions: DEFAULT_HASH_FUNCTIONS,
+            bucket_size: DEFAULT_BUCKET_SIZE,
             stash_capacity: DEFAULT_STASH_SIZE,
-            primary_random: rand::thread_rng().gen::<u16>(),
-            secondary_random: rand::thread_rng().gen::<u16>(),
+            eviction: Eviction::RandomWalk,
+            max_displacements: None,
+            stash_overflow: StashOverflow::Rehash,
+            max_rehash_attempts: DEFAULT_MAX_REHASH_ATTEMPTS,
+            grow_after_failed_rehashes: None,
+            min_load_factor: None,
+            growth_factor: DEFAULT_GROWTH_FACTOR,
+            rng: StdRng::from_entropy(),
+            hash_builder: SipBuildHasher::new(),
         }
     }
 
-    pub fn with_hash_capacity(mut self, capacity: u16) -> CuckooHashBuilder<U> {
+    /// draw the table's random numbers from a generator seeded with seed,
+    /// so that the same inserts place entries and rehash the same way
+    /// every time. Keys are hashed with a SipHash keyed from seed too.
+    /// The generator is kept by with_hasher, so seed first to use another
+    /// hasher, which has to be seeded as well for the table to be
+    /// reproducible
+    pub fn with_seed(mut self, seed: u64) -> CuckooHashBuilder<K, U> {
+        self.rng = StdRng::seed_from_u64(seed);
+        self.hash_builder = SipBuildHasher::with_keys(self.rng.gen(), self.rng.gen());
+        self
+    }
+
+    /// as with_seed, seeding the table's generator from rng
+    pub fn with_rng<R: RngCore>(mut self, rng: R) -> CuckooHashBuilder<K, U> {
+        self.rng = StdRng::from_rng(rng).expect("rng failed to seed the table");
+        self.hash_builder = SipBuildHasher::with_keys(self.rng.gen(), self.rng.gen());
+        self
+    }
+}
+
+impl<K: Default, U> Default for CuckooHashBuilder<K, U> {
+    fn default() -> CuckooHashBuilder<K, U> {
+        CuckooHashBuilder::new()
+    }
+}
+
+impl<K: Default, U, S> CuckooHashBuilder<K, U, S> {
+    /// number of buckets in each table
+    pub fn with_hash_capacity(mut self, capacity: usize) -> CuckooHashBuilder<K, U, S> {
         self.hash_capacity = capacity;
         self
     }
 
-    pub fn with_stash_capacity(mut self, capacity: u16) -> CuckooHashBuilder<U> {
+    /// place keys with d hash functions, each with a table of its own.
+    /// A lookup probes up to d buckets, in return the tables can be
+    /// filled to about 91% with 3 and 97% with 4, against under 50% with
+    /// the default of 2
+    pub fn with_hash_functions(mut self, d: usize) -> CuckooHashBuilder<K, U, S> {
+        assert!(d >= 2, "cuckoo hashing needs at least two hash functions");
+        self.hash_functions = d;
+        self
+    }
+
+    /// hold up to bucket_size entries in each slot of the tables. With 4
+    /// two tables can be filled to over 90%, against under 50% with the
+    /// default of 1
+    pub fn with_bucket_size(mut self, bucket_size: usize) -> CuckooHashBuilder<K, U, S> {
+        assert!(bucket_size > 0, "bucket size must be positive");
+        self.bucket_size = bucket_size;
+        self
+    }
+
+    /// slots in the stash, which holds entries eviction finds no room
+    /// for. DEFAULT_STASH_SIZE unless set
+    pub fn with_stash_capacity(mut self, capacity: usize) -> CuckooHashBuilder<K, U, S> {
         self.stash_capacity = capacity;
         self
     }
 
-    pub fn build(self) -> CuckooHash<U> {
-        let mut hash = CuckooHash::<U> {
-            primary: Vec::with_capacity(self.hash_capacity.into()),
-            secondary: Vec::with_capacity(self.hash_capacity.into()),
-            stash: Vec::with_capacity(self.stash_capacity.into()),
+    /// how to make room for a key whose buckets are all full, by a
+    /// random walk by default
+    pub fn with_eviction(mut self, eviction: Eviction) -> CuckooHashBuilder<K, U, S> {
+        self.eviction = eviction;
+        self
+    }
+
+    /// displacements an insert makes before falling back on the stash,
+    /// or the longest path breadth-first eviction searches for.
+    /// MAX_SECONDARY_HASH_ITERATIONS per hash function unless set
+    pub fn with_max_displacements(mut self, displacements: usize) -> CuckooHashBuilder<K, U, S> {
+        self.max_displacements = Some(displacements);
+        self
+    }
+
+    /// what to do when eviction finds no room and the stash is full, a
+    /// rehash by default
+    pub fn with_stash_overflow(mut self, overflow: StashOverflow) -> CuckooHashBuilder<K, U, S> {
+        self.stash_overflow = overflow;
+        self
+    }
+
+    /// number of new sets of hash functions a rehash tries before an
+    /// insert fails with RehashFailed
+    pub fn with_max_rehash_attempts(mut self, attempts: usize) -> CuckooHashBuilder<K, U, S> {
+        self.max_rehash_attempts = attempts;
+        self
+    }
+
+    /// grow the tables once a rehash has drawn new hash functions this
+    /// many times in a row without placing every entry, instead of only
+    /// failing the insert with RehashFailed once the maximum number of
+    /// rehash attempts is used up. Clamped below the maximum number of
+    /// rehash attempts, so a rehash always gets to grow the tables
+    pub fn with_grow_after_failed_rehashes(
+        mut self,
+        failed_rehashes: usize,
+    ) -> CuckooHashBuilder<K, U, S> {
+        assert!(failed_rehashes > 0, "failed rehashes must be positive");
+        self.grow_after_failed_rehashes = Some(failed_rehashes);
+        self
+    }
+
+    /// shrink the tables once less than load_factor of their slots are
+    /// in use
+    pub fn with_min_load_factor(mut self, load_factor: f32) -> CuckooHashBuilder<K, U, S> {
+        assert!(
+            (0.0..1.0).contains(&load_factor),
+            "min load factor must be in [0, 1)"
+        );
+        self.min_load_factor = Some(load_factor);
+        self
+    }
+
+    /// factor hash capacity is multiplied by when growing, and divided by
+    /// when shrinking. Must be greater than 1
+    pub fn with_growth_factor(mut self, growth_factor: f32) -> CuckooHashBuilder<K, U, S> {
+        assert!(growth_factor > 1.0, "growth factor must be greater than 1");
+        self.growth_factor = growth_factor;
+        self
+    }
+
+    /// hash keys with hash_builder rather than a randomly keyed SipHash.
+    /// Any BuildHasher will do, as will the hashers in hash_table::hashers
+    pub fn with_hasher<T>(self, hash_builder: T) -> CuckooHashBuilder<K, U, T> {
+        CuckooHashBuilder::<K, U, T> {
+            _phantom_k: PhantomData {},
+            _phantom_u: PhantomData {},
             hash_capacity: self.hash_capacity,
+            hash_functions: self.hash_functions,
+            bucket_size: self.bucket_size,
             stash_capacity: self.stash_capacity,
-            primary_random: 0,
-            secondary_random: 0,
+            eviction: self.eviction,
+            max_displacements: self.max_displacements,
+            stash_overflow: self.stash_overflow,
+            max_rehash_attempts: self.max_rehash_attempts,
+            grow_after_failed_rehashes: self.grow_after_failed_rehashes,
+            min_load_factor: self.min_load_factor,
+            growth_factor: self.growth_factor,
+            rng: self.rng,
+            hash_builder,
+        }
+    }
+
+    pub fn build(self) -> CuckooHash<K, U, S> {
+        let mut hash = CuckooHash::<K, U, S> {
+            tables: Vec::with_capacity(self.hash_functions),
+            stash: Vec::with_capacity(self.stash_capacity),
+            hash_capacity: self.hash_capacity,
+            bucket_size: self.bucket_size,
+            stash_capacity: self.stash_capacity,
+            len: 0,
+            stash_len: 0,
+            randoms: vec![0; self.hash_functions],
+            hash_fns: vec![Tabulation::with_seed(0); self.hash_functions],
+            eviction: self.eviction,
+            max_displacements: self
+                .max_displacements
+                .unwrap_or(MAX_SECONDARY_HASH_ITERATIONS as usize * self.hash_functions),
+            stash_overflow: self.stash_overflow,
+            rehashes: 0,
+            max_rehash_attempts: self.max_rehash_attempts,
+            // a rehash counts its failures only while it lasts, so it
+            // must fail fewer times than it makes attempts for it to grow
+            grow_after_failed_rehashes: self.grow_after_failed_rehashes.map(|failed| {
+                failed
+                    .min(self.max_rehash_attempts.saturating_sub(1))
+                    .max(1)
+            }),
+            min_load_factor: self.min_load_factor,
+            growth_factor: self.growth_factor,
+            rng: self.rng,
+            hash_builder: self.hash_builder,
         };
 
-        // initialize the primary and secondary hash tables
-        for _i in 0..self.hash_capacity {
-            let h = HashTableEntry::<U>::default();
-            hash.primary.push(h);
-            let h = HashTableEntry::<U>::default();
-            hash.secondary.push(h);
+        // initialize the hash tables
+        for _t in 0..self.hash_functions {
+            let mut table = Vec::with_capacity(self.hash_capacity * self.bucket_size);
+            for _i in 0..self.hash_capacity * self.bucket_size {
+                table.push(HashTableEntry::<K, U>::default());
+            }
+            hash.tables.push(table);
         }
 
         // initialize the stash
         for _i in 0..self.stash_capacity {
-            let h = HashTableEntry::<U>::default();
+            let h = HashTableEntry::<K, U>::default();
             hash.stash.push(h);
         }
 
-        hash.update_primary_random();
-        hash.update_secondary_random();
+        for t in 0..self.hash_functions {
+            hash.update_random(t);
+        }
 
         hash
     }
 }
 
-impl<U: Default + std::marker::Copy + std::fmt::Debug> HashTable<U> for CuckooHash<U> {
-
-    fn insert(&mut self, key: u16, data: U) -> Result<(), HashTableError> {
-        let ret = self.lookup(key);
-        if ret.is_ok() {
-            return Ok(());
-        }
-        let mut p_key = key;
-        let mut p_data = data;
-        for _ in 0..MAX_SECONDARY_HASH_ITERATIONS {
-            let h1: usize = self.hash(p_key).into();
-            if self.primary[h1].data.is_none() {
-                self.primary[h1].key = key;
-                self.primary[h1].data = Some(Box::new(p_data));
-                return Ok(());
-            }
-            let temp_key = self.primary[h1].key;
-            let temp_data = **self.primary[h1].data.as_ref().unwrap();
-            self.primary[h1].key = p_key;
-            self.primary[h1].data = Some(Box::new(p_data));
-            p_key = temp_key;
-            p_data = temp_data;
-            let h2: usize = self.secondary_hash(p_key).into();
-            if self.secondary[h2].data.is_none() {
-                self.secondary[h2].key = p_key;
-                self.secondary[h2].data = Some(Box::new(p_data));
-                return Ok(());
-            }
-            let temp_key = self.secondary[h2].key;
-            let temp_data = **self.secondary[h2].data.as_ref().unwrap();
-            self.secondary[h2].key = p_key;
-            self.secondary[h2].data = Some(Box::new(p_data));
-            p_key = temp_key;
-            p_data = temp_data;
-        }
-        // was unable to insert after some number of attempts
-        // see if we can insert in stash. If not, then rehash
-        // everything and try again from the top
-        match self.insert_stash(p_key, p_data) {
-            Ok(_) => {},
-            _ => {
-                self.rehash()?;
-                self.insert(p_key, p_data)?;
-            },
+impl<K: Hash + Eq + Default, U, S: HashFn<K>> HashTable<K, U> for CuckooHash<K, U, S> {
+    fn insert(&mut self, key: K, data: U) -> Result<Option<U>, HashTableError> {
+        match self.entry(key)? {
+            Entry::Occupied(mut entry) => Ok(Some(entry.insert(data))),
+            Entry::Vacant(entry) => {
+                entry.insert(data);
+                Ok(None)
+            }
         }
-        Ok(())
     }
 
-    /// delete means finding the value in either primary or
-    /// secondary tables and clearing it, which is O(1)
-    fn delete(&mut self, key: u16) -> Result<(), HashTableError> {
-        let x: usize = self.hash(key).into();
-        if self.primary[x].data.is_none() || self.primary[x].key != key {
-            let x: usize = self.secondary_hash(key).into();
-            if self.secondary[x].data.is_none() || self.secondary[x].key != key {
-                // delete stash, if present
-                self.delete_stash(key); 
-                return Ok(());
-            } else {
-                let h = HashTableEntry::<U>::default();
-                self.secondary[x] = h;
-                return Ok(());
-            }
-        } else {
-            let h = HashTableEntry::<U>::default();
-            self.primary[x] = h;
-            return Ok(());
+    /// remove means finding the value in one of the tables and clearing
+    /// it, which is O(1)
+    fn remove(&mut self, key: &K) -> Result<U, HashTableError> {
+        let slot = self.find(key).ok_or(HashTableError::NotFound)?;
+        Ok(*self.take(slot))
+    }
+
+    /// get means finding the value in one of the tables and is O(1),
+    /// probing at most one bucket per hash function
+    fn get(&self, key: &K) -> Option<&U> {
+        let slot = self.find(key)?;
+        self.entry_at(slot).data.as_deref()
+    }
+
+    fn get_mut(&mut self, key: &K) -> Option<&mut U> {
+        let slot = self.find(key)?;
+        self.entry_at_mut(slot).data.as_deref_mut()
+    }
+
+    fn len(&self) -> usize {
+        self.len
+    }
+
+    fn clear(&mut self) {
+        self.drain();
+    }
+
+    /// slots across the tables and the stash
+    fn capacity(&self) -> usize {
+        self.slots() + self.get_stash_capacity()
+    }
+}
+
+impl<K, U, S: HashFn<K>> CuckooHash<K, U, S> {
+    /// the bucket key hashes to in the first table
+    fn hash(&self, key: &K) -> usize {
+        self.index(0, self.hash_builder.hash(key))
+    }
+}
+
+impl<K: Hash + Eq + Default, U, S: HashFn<K>> CuckooHash<K, U, S> {
+    /// get the entry for key for in-place manipulation, hashing the key
+    /// only once. Room for an absent key is made up front, displacing
+    /// other entries and rehashing as an insert would, so this fails if
+    /// the key cannot be added
+    pub fn entry(&mut self, key: K) -> Result<Entry<'_, K, U, S>, HashTableError> {
+        let hash = self.hash_builder.hash(&key);
+        if let Some(slot) = self.find_hashed(&key, hash) {
+            return Ok(Entry::Occupied(OccupiedEntry { table: self, slot }));
         }
+        let slot = self.make_room(hash)?;
+        Ok(Entry::Vacant(VacantEntry {
+            table: self,
+            key,
+            slot,
+        }))
     }
 
-    /// lookup means finding the value in either primary or
-    /// secondary tables and is O(1)
-    fn lookup(&self, key: u16) -> Result<U, HashTableError>
+    /// the slot holding key, in one of the tables or the stash
+    fn find(&self, key: &K) -> Option<Slot> {
+        self.find_hashed(key, self.hash_builder.hash(key))
+    }
+
+    fn find_hashed(&self, key: &K, hash: u64) -> Option<Slot> {
+        let holds = |entry: &HashTableEntry<K, U>| entry.data.is_some() && entry.key == *key;
+        if let Some(slot) = self.find_in_tables(hash, holds) {
+            return Some(slot);
+        }
+        let index = self.lookup_stash(key).ok()?;
+        Some(Slot::Stash(index))
+    }
+
+    /// the first slot, in the buckets hash maps to across the tables,
+    /// whose entry satisfies holds
+    fn find_in_tables<F>(&self, hash: u64, holds: F) -> Option<Slot>
     where
-        U: Copy,
+        F: Fn(&HashTableEntry<K, U>) -> bool,
     {
-        let x: usize = self.hash(key).into();
-        if self.primary[x].data.is_none() || self.primary[x].key != key {
-            let x: usize = self.secondary_hash(key).into();
-            if self.secondary[x].data.is_none() || self.secondary[x].key != key {
-                // check the stash
-                match self.lookup_stash(key).into() {
-                    Ok(index) => {return Ok(**self.stash[index].data.as_ref().unwrap());},
-                    Err(_) => {return Err(HashTableError::NotFound);},
-                };
-/*
-                let index: usize = self.lookup_stash(key).into() {
-                    return Ok(**self.stash[index].data.as_ref().unwrap());
-                } else {
-                    return Err(HashTableError::NotFound);
-                }
-*/
-            } else {
-                return Ok(**self.secondary[x].data.as_ref().unwrap());
-            }
-        } else {
-            return Ok(**self.primary[x].data.as_ref().unwrap());
+        if self.get_hash_capacity() == 0 {
+            return None;
         }
+        (0..self.tables.len()).find_map(|t| {
+            self.bucket(self.index(t, hash))
+                .find(|x| holds(&self.tables[t][*x]))
+                .map(|x| Slot::Table(t, x))
+        })
     }
-}
 
-impl<U: std::marker::Copy> HashFn for CuckooHash<U> {
-    fn hash(&self, key: u16) -> u16 {
-        (key & self.get_primary_random()) % self.get_hash_capacity()
+    /// clear an occupied slot, returning its data, and shrink the table
+    /// if that leaves it below the minimum load factor
+    pub(crate) fn take(&mut self, slot: Slot) -> Box<U> {
+        if let Slot::Stash(_) = slot {
+            self.stash_len -= 1;
+        }
+        self.len -= 1;
+        let data = self.entry_at_mut(slot).data.take().unwrap();
+        self.shrink_to_fit_load();
+        data
     }
-}
-
-impl<U: Default + std::fmt::Debug + std::marker::Copy> CuckooHash<U> {
 
-    fn lookup_stash(&self, key: u16) -> Result<usize, HashTableError> {
-        for i in 0..self.stash_capacity as usize {
-            let ent = &self.stash[i];
-            if ent.data.is_some() && ent.key == key {
-                // already exists
-                return Ok(i);   
+    /// free a slot for a key known not to be in the table, hashed to
+    /// hash, by the table's eviction, then in the stash, then as the
+    /// stash overflow policy says. Displacements are found before any
+    /// entry is moved, so if no room is found the table is left as it was
+    fn make_room(&mut self, hash: u64) -> Result<Slot, HashTableError> {
+        let path = match self.eviction {
+            Eviction::BreadthFirst => self.shortest_path(hash),
+            _ => self.walk(hash),
+        };
+        if let Some(path) = path {
+            // move every entry on the path one step along, freeing the
+            // slot it starts from
+            for w in path.windows(2).rev() {
+                let ((t, y), (t2, y2)) = (w[0], w[1]);
+                self.tables[t2][y2] = mem::take(&mut self.tables[t][y]);
             }
+            let (t, y) = path[0];
+            return Ok(Slot::Table(t, y));
+        }
+        // was unable to find room after some number of displacements
+        // see if there is room in the stash. If not, then rehash
+        // everything along with the new key
+        if let Some(x) = self.vacant_stash_slot() {
+            return Ok(Slot::Stash(x));
         }
-        return Err(HashTableError::NotFound);
+        let capacity = match self.stash_overflow {
+            StashOverflow::Rehash => self.get_hash_capacity(),
+            StashOverflow::Grow => self.grown_capacity(self.get_hash_capacity()),
+            StashOverflow::Error => return Err(HashTableError::TableFull),
+        };
+        self.rehash(hash, capacity)
+            .ok_or(HashTableError::RehashFailed)
     }
 
-    fn delete_stash(&mut self, key: u16) {
-        for i in 0..self.stash_capacity as usize {
-            let ent = &self.stash[i];
-            if ent.data.is_some() && ent.key == key {
-                let h = HashTableEntry::<U>::default();
-                self.stash[i] = h;
+    /// while all of the buckets the carried entry hashes to are full,
+    /// displace the entry of one and carry that on, for up to
+    /// max_displacements displacements. Greedy eviction goes round the
+    /// tables in order, random-walk eviction picks one at random.
+    /// Returns the slots displaced from, ending in a vacant one, as
+    /// (table, slot) pairs. Nothing is moved, so a walk that comes back
+    /// to a slot it displaced from drops the loop it made
+    fn walk(&mut self, hash: u64) -> Option<Vec<(usize, usize)>> {
+        if self.get_hash_capacity() == 0 {
+            return None;
+        }
+        let mut path = Vec::new();
+        let mut p_hash = hash;
+        // the table the carried entry was displaced from
+        let mut from = None;
+        let d = self.tables.len();
+        for displacements in 0..=self.max_displacements {
+            let t = displacing_table(self.eviction, &mut self.rng, d, from);
+            let probed = probed_tables(self.eviction, d, t);
+            if let Some(slot) = self.vacant_slot(probed, p_hash) {
+                path.push(slot);
+                return Some(path);
+            }
+            if displacements == self.max_displacements {
                 break;
             }
+            let bucket = self.index(t, p_hash);
+            let x = victim(&mut self.rng, bucket, self.bucket_size);
+            if let Some(i) = path.iter().position(|slot| *slot == (t, x)) {
+                path.truncate(i);
+            }
+            path.push((t, x));
+            p_hash = self.hash_builder.hash(&self.tables[t][x].key);
+            from = Some(t);
         }
+        None
     }
 
-    fn insert_stash(&mut self, key: u16, data: U) -> Result<(), HashTableError> {
-        if self.lookup_stash(key).is_ok() {
-            return Ok(());
-        }
-        for i in 0..self.stash_capacity as usize {
+    /// the shortest chain of displacements from the buckets hash maps
+    /// to, ending in a vacant slot
+    fn shortest_path(&self, hash: u64) -> Option<Vec<(usize, usize)>> {
+        shortest_path(
+            hash,
+            &self.hash_fns,
+            self.get_hash_capacity(),
+            self.bucket_size,
+            self.max_displacements,
+            |t, y| {
+                let entry = &self.tables[t][y];
+                entry
+                    .data
+                    .as_ref()
+                    .map(|_| self.hash_builder.hash(&entry.key))
+            },
+        )
+    }
+
+    /// the first vacant slot in the buckets hash maps to in the given
+    /// tables
+    fn vacant_slot(&self, tables: Range<usize>, hash: u64) -> Option<(usize, usize)> {
+        tables.into_iter().find_map(|t| {
+            self.bucket(self.index(t, hash))
+                .find(|x| self.tables[t][*x].data.is_none())
+                .map(|x| (t, x))
+        })
+    }
+
+    fn lookup_stash(&self, key: &K) -> Result<usize, HashTableError> {
+        for i in 0..self.stash_capacity {
             let ent = &self.stash[i];
-            if ent.data.is_none() {
-                self.stash[i].key = key;
-                self.stash[i].data = Some(Box::new(data));
-                return Ok(());   
+            if ent.data.is_some() && ent.key == *key {
+                // already exists
+                return Ok(i);
             }
         }
-        return Err(HashTableError::TableFull); 
+        Err(HashTableError::NotFound)
+    }
+
+    /// a vacant slot of the stash, if it is not full
+    fn vacant_stash_slot(&self) -> Option<usize> {
+        self.stash.iter().position(|entry| entry.data.is_none())
     }
 
     fn initialize_hash_tables(&mut self) {
-        // initialize the primary and secondary hash tables
-        for _i in 0..self.hash_capacity {
-            let h = HashTableEntry::<U>::default();
-            self.primary.push(h);
-            let h = HashTableEntry::<U>::default();
-            self.secondary.push(h);
+        // initialize the hash tables
+        for table in self.tables.iter_mut() {
+            for _i in 0..self.hash_capacity * self.bucket_size {
+                let h = HashTableEntry::<K, U>::default();
+                table.push(h);
+            }
         }
     }
-    // implementation of a second hash function, not a part of the
-    // hash table trait, but needed to get a different hash result
-    // across the secondary hash table
-    fn secondary_hash(&self, key: u16) -> u16 {
-        (key & self.get_secondary_random()) % self.get_hash_capacity()
+
+    fn initialize_stash(&mut self) {
+        for _i in 0..self.stash_capacity {
+            let h = HashTableEntry::<K, U>::default();
+            self.stash.push(h);
+        }
     }
 
-    /// rehash the primary and secondary tables with new hash functions
-    fn rehash(&mut self) -> Result<(), HashTableError> {
-        // a new hash function is defined as new random values for each
-        // table (hash fns in this impl are computed using prime table size
-        // and a random number.
+    /// rehash every entry, in the tables and the stash, along with a new
+    /// key hashed to hash that found no room, under new hash functions
+    /// into tables of capacity buckets. Placement is planned for up to
+    /// max_rehash_attempts sets of hash functions before any entry is
+    /// moved, growing the tables whenever grow_after_failed_rehashes
+    /// plans in a row fail. Returns the slot left vacant for the new
+    /// key, or None, with the table left as it was, if no plan places
+    /// every entry
+    fn rehash(&mut self, hash: u64, capacity: usize) -> Option<Slot> {
+        let mut hashes = self.entry_hashes();
+        hashes.push(hash);
+
+        let mut capacity = capacity;
+        let mut failures = 0;
+        for _attempt in 0..self.max_rehash_attempts {
+            if self.grow_after_failed_rehashes == Some(failures) {
+                capacity = self.grown_capacity(capacity);
+                failures = 0;
+            }
+            if let Some(plan) = self.draw_plan(&hashes, capacity) {
+                return self.apply_plan(plan);
+            }
+            failures += 1;
+        }
+        None
+    }
 
-        // create new hash functions by generating new random number for each
-        self.update_primary_random();
-        self.update_secondary_random();
+    /// rehash into smaller tables if entries have fallen below the
+    /// minimum load factor. The smaller tables are kept at most half
+    /// full, which cuckoo hashing sustains for any number of tables and
+    /// bucket size
+    fn shrink_to_fit_load(&mut self) {
+        let min_load_factor = match self.min_load_factor {
+            Some(min_load_factor) => min_load_factor,
+            None => return,
+        };
+        if (self.len as f32) >= self.slots() as f32 * min_load_factor {
+            return;
+        }
+        let capacity = (self.get_hash_capacity() as f32 / self.growth_factor).floor() as usize;
+        let slots = self.tables.len() * capacity * self.bucket_size;
+        if capacity >= self.get_hash_capacity() || 2 * self.len > slots {
+            return;
+        }
+        let hashes = self.entry_hashes();
+        for _attempt in 0..self.max_rehash_attempts {
+            if let Some(plan) = self.draw_plan(&hashes, capacity) {
+                self.apply_plan(plan);
+                return;
+            }
+        }
+    }
 
-        // get all entries to rehash
+    /// slots across the tables, leaving out the stash
+    fn slots(&self) -> usize {
+        self.tables.len() * self.get_hash_capacity() * self.bucket_size
+    }
 
-        let p = mem::take(&mut self.primary);
-        let s = mem::take(&mut self.secondary);
+    fn grown_capacity(&self, capacity: usize) -> usize {
+        let grown = (capacity as f32 * self.growth_factor).ceil() as usize;
+        grown.max(capacity + 1)
+    }
 
-        self.initialize_hash_tables();
+    /// hashes of the entries in table order, then the stash
+    fn entry_hashes(&self) -> Vec<u64> {
+        let mut hashes = Vec::with_capacity(self.len + 1);
+        for entry in self.tables.iter().flatten().chain(&self.stash) {
+            if entry.data.is_some() {
+                hashes.push(self.hash_builder.hash(&entry.key));
+            }
+        }
+        hashes
+    }
 
-        // iterate the list of entries and re-add them to the hash table
+    /// plan a rehash into tables of the given capacity under a new set
+    /// of hash functions. A new hash function is new random tables for
+    /// the tabulation hash of each table, drawn with a new random number
+    fn draw_plan(&mut self, hashes: &[u64], capacity: usize) -> Option<RehashPlan> {
+        let randoms = (0..self.tables.len())
+            .map(|_| self.rng.gen::<u64>())
+            .collect();
+        self.rehashes += 1;
+        let mut plan = RehashPlan::new(randoms, capacity, self.bucket_size);
+        let placed = plan.place(
+            hashes,
+            self.get_stash_capacity(),
+            self.eviction,
+            self.max_displacements,
+            &mut self.rng,
+        );
+        placed.then_some(plan)
+    }
 
-        // unwrap is safe in below code because we have Some(_)
+    /// switch to the capacity and hash functions of plan, moving every
+    /// entry to where plan puts it. Returns the slot plan puts a hash
+    /// planned after those of the entries in, left vacant, if any
+    fn apply_plan(&mut self, plan: RehashPlan) -> Option<Slot> {
+        let tables = mem::take(&mut self.tables);
+        let st = mem::take(&mut self.stash);
+        let mut entries: Vec<Option<HashTableEntry<K, U>>> = tables
+            .into_iter()
+            .flatten()
+            .chain(st)
+            .filter(|entry| entry.data.is_some())
+            .map(Some)
+            .collect();
 
-        for mut val in p {
-            match val.data {
-                Some(_) => { 
-                    self.insert(val.key, *mem::take(&mut val.data).unwrap())?;
-                },
-                _ => {},
+        self.hash_capacity = plan.hash_capacity;
+        self.tables = (0..plan.tables.len()).map(|_| Vec::new()).collect();
+        self.randoms = plan.randoms;
+        self.hash_fns = plan.hash_fns;
+        self.initialize_hash_tables();
+        self.initialize_stash();
+        let mut vacant = None;
+        for (t, table) in plan.tables.into_iter().enumerate() {
+            for (x, i) in table.into_iter().enumerate() {
+                let Some(i) = i else { continue };
+                match entries.get_mut(i) {
+                    Some(entry) => self.tables[t][x] = entry.take().unwrap(),
+                    None => vacant = Some(Slot::Table(t, x)),
+                }
             }
         }
-
-        for mut val in s {
-            match val.data {
-                Some(_) => { 
-                    self.insert(val.key, *mem::take(&mut val.data).unwrap())?;
-                },
-                _ => {},
+        self.stash_len = 0;
+        for (x, i) in plan.stash.into_iter().enumerate() {
+            match entries.get_mut(i) {
+                Some(entry) => {
+                    self.stash[x] = entry.take().unwrap();
+                    self.stash_len += 1;
+                }
+                None => vacant = Some(Slot::Stash(x)),
             }
         }
-
-        Ok(())
+        vacant
     }
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
+    use hash_table::hashers::SipBuildHasher;
+    use hash_table::testing::{check_table, TableBuilder};
+    use std::hash::{BuildHasherDefault, Hasher};
+
+    // passes integer keys through unchanged, so the statistical tests
+    // exercise the cuckoo hash functions rather than the key hasher
+    #[derive(Default)]
+    struct IdentityHasher(u64);
+
+    impl Hasher for IdentityHasher {
+        fn write(&mut self, bytes: &[u8]) {
+            for byte in bytes {
+                self.0 = (self.0 << 8) | *byte as u64;
+            }
+        }
+
+        fn write_u64(&mut self, i: u64) {
+            self.0 = i;
+        }
+
+        fn finish(&self) -> u64 {
+            self.0
+        }
+    }
+
+    type IdentityState = BuildHasherDefault<IdentityHasher>;
+
+    struct Builder;
+
+    impl TableBuilder for Builder {
+        type Table<K: Hash + Eq + Default, U, S: HashFn<K>> = CuckooHash<K, U, S>;
+
+        fn build<K: Hash + Eq + Default, U, S: HashFn<K>>(
+            &self,
+            hash_builder: S,
+        ) -> CuckooHash<K, U, S> {
+            CuckooHashBuilder::new()
+                .with_hash_capacity(8)
+                .with_grow_after_failed_rehashes(4)
+                .with_hasher(hash_builder)
+                .build()
+        }
+    }
+
+    #[test]
+    fn passes_table_tests() {
+        check_table(Builder);
+    }
 
     #[test]
     fn can_create_cuckoo_hash() {
-        let mut x = CuckooHashBuilder::<u16>::new()
+        let mut x = CuckooHashBuilder::<u16, u16>::new()
             .with_hash_capacity(3)
             .build();
         assert_eq!(x.get_hash_capacity(), 3);
@@ -339,7 +1102,7 @@ mod tests {
 
     #[test]
     fn can_create_cuckoo_hash_and_search() {
-        let mut x = CuckooHashBuilder::<u16>::new()
+        let mut x = CuckooHashBuilder::<u16, u16>::new()
             .with_hash_capacity(3)
             .build();
 
@@ -353,25 +1116,25 @@ mod tests {
         item += 10;
         assert!(x.insert(4, item).is_ok());
 
-        let ret = x.lookup(1);
+        let ret = x.lookup(&1);
         assert!(ret.is_ok());
         assert_eq!(ret.unwrap(), 10);
-        let ret = x.lookup(2);
+        let ret = x.lookup(&2);
         assert!(ret.is_ok());
         assert_eq!(ret.unwrap(), 20);
-        let ret = x.lookup(3);
+        let ret = x.lookup(&3);
         assert!(ret.is_ok());
         assert_eq!(ret.unwrap(), 30);
-        let ret = x.lookup(4);
+        let ret = x.lookup(&4);
         assert!(ret.is_ok());
         assert_eq!(ret.unwrap(), 40);
-        let ret = x.lookup(17);
-        assert_eq!(ret.is_ok(), false);
+        let ret = x.lookup(&17);
+        assert!(ret.is_err());
     }
 
     #[test]
     fn can_create_cuckoo_hash_and_delete() {
-        let mut x = CuckooHashBuilder::<u16>::new()
+        let mut x = CuckooHashBuilder::<u16, u16>::new()
             .with_hash_capacity(3)
             .build();
         assert_eq!(x.get_hash_capacity(), 3);
@@ -388,49 +1151,49 @@ mod tests {
         item += 10;
         assert!(x.insert(6, item).is_ok());
 
-        let ret = x.lookup(1);
+        let ret = x.lookup(&1);
         assert!(ret.is_ok());
         assert_eq!(ret.unwrap(), 10);
-        let ret = x.lookup(2);
+        let ret = x.lookup(&2);
         assert!(ret.is_ok());
         assert_eq!(ret.unwrap(), 20);
-        let ret = x.lookup(3);
+        let ret = x.lookup(&3);
         assert!(ret.is_ok());
         assert_eq!(ret.unwrap(), 30);
-        let ret = x.lookup(4);
+        let ret = x.lookup(&4);
         assert!(ret.is_ok());
         assert_eq!(ret.unwrap(), 40);
-        let ret = x.lookup(5);
+        let ret = x.lookup(&5);
         assert!(ret.is_ok());
         assert_eq!(ret.unwrap(), 50);
-        let ret = x.lookup(6);
+        let ret = x.lookup(&6);
         assert!(ret.is_ok());
         assert_eq!(ret.unwrap(), 60);
 
-        let ret = x.delete(1);
+        let ret = x.delete(&1);
         assert!(ret.is_ok());
-        let ret = x.lookup(1);
-        assert_eq!(ret.is_ok(), false);
-        let ret = x.delete(2);
+        let ret = x.lookup(&1);
+        assert!(ret.is_err());
+        let ret = x.delete(&2);
         assert!(ret.is_ok());
-        let ret = x.lookup(2);
-        assert_eq!(ret.is_ok(), false);
-        let ret = x.delete(3);
+        let ret = x.lookup(&2);
+        assert!(ret.is_err());
+        let ret = x.delete(&3);
         assert!(ret.is_ok());
-        let ret = x.lookup(3);
-        assert_eq!(ret.is_ok(), false);
-        let ret = x.delete(4);
+        let ret = x.lookup(&3);
+        assert!(ret.is_err());
+        let ret = x.delete(&4);
         assert!(ret.is_ok());
-        let ret = x.lookup(4);
-        assert_eq!(ret.is_ok(), false);
-        let ret = x.delete(5);
+        let ret = x.lookup(&4);
+        assert!(ret.is_err());
+        let ret = x.delete(&5);
         assert!(ret.is_ok());
-        let ret = x.lookup(5);
-        assert_eq!(ret.is_ok(), false);
-        let ret = x.delete(6);
+        let ret = x.lookup(&5);
+        assert!(ret.is_err());
+        let ret = x.delete(&6);
         assert!(ret.is_ok());
-        let ret = x.lookup(6);
-        assert_eq!(ret.is_ok(), false);
+        let ret = x.lookup(&6);
+        assert!(ret.is_err());
 
         // see if we can insert them again
 
@@ -441,27 +1204,740 @@ mod tests {
         item += 100;
         assert!(x.insert(3, item).is_ok());
 
-        let ret = x.lookup(1);
+        let ret = x.lookup(&1);
         assert!(ret.is_ok());
         assert_eq!(ret.unwrap(), 100);
-        let ret = x.lookup(2);
+        let ret = x.lookup(&2);
         assert!(ret.is_ok());
         assert_eq!(ret.unwrap(), 200);
-        let ret = x.lookup(3);
+        let ret = x.lookup(&3);
         assert!(ret.is_ok());
         assert_eq!(ret.unwrap(), 300);
 
-        let ret = x.delete(1);
+        let ret = x.delete(&1);
         assert!(ret.is_ok());
-        let ret = x.lookup(1);
-        assert_eq!(ret.is_ok(), false);
-        let ret = x.delete(2);
+        let ret = x.lookup(&1);
+        assert!(ret.is_err());
+        let ret = x.delete(&2);
         assert!(ret.is_ok());
-        let ret = x.lookup(2);
-        assert_eq!(ret.is_ok(), false);
-        let ret = x.delete(3);
+        let ret = x.lookup(&2);
+        assert!(ret.is_err());
+        let ret = x.delete(&3);
         assert!(ret.is_ok());
-        let ret = x.lookup(3);
-        assert_eq!(ret.is_ok(), false);
+        let ret = x.lookup(&3);
+        assert!(ret.is_err());
+    }
+
+    #[test]
+    fn vacant_entries_displace_earlier_keys() {
+        // later keys displace earlier ones, the reference handed back
+        // must still point at the data of the key inserted
+        let mut x = CuckooHashBuilder::<u16, u16>::new()
+            .with_hash_capacity(3)
+            .build();
+        for key in 0..10 {
+            *x.entry(key).unwrap().or_default() += key;
+        }
+        assert_eq!(x.len(), 10);
+        for key in 0..10 {
+            assert_eq!(x.get(&key), Some(&key));
+        }
+    }
+
+    #[test]
+    fn can_iterate_cuckoo_hash() {
+        // a stash big enough to take every entry, so iteration covers the
+        // tables and the stash whichever way the keys land
+        let mut x = CuckooHashBuilder::<u16, u16>::new()
+            .with_hash_capacity(3)
+            .with_stash_capacity(6)
+            .build();
+        for key in 0..6 {
+            assert!(x.insert(key, key * 10).is_ok());
+        }
+        assert!(x.delete(&3).is_ok());
+
+        let mut entries: Vec<(u16, u16)> = x.iter().map(|(k, v)| (*k, *v)).collect();
+        entries.sort();
+        assert_eq!(entries, vec![(0, 0), (1, 10), (2, 20), (4, 40), (5, 50)]);
+        let mut keys: Vec<u16> = x.keys().copied().collect();
+        keys.sort();
+        assert_eq!(keys, vec![0, 1, 2, 4, 5]);
+        assert_eq!(x.values().sum::<u16>(), 120);
+
+        for (key, data) in x.iter_mut() {
+            *data += key;
+        }
+        for (_, data) in &mut x {
+            *data += 1;
+        }
+        assert_eq!((&x).into_iter().count(), 5);
+        assert_eq!(x.get(&5), Some(&56));
+
+        let mut drained: Vec<(u16, u16)> = x.drain().collect();
+        drained.sort();
+        assert_eq!(drained, vec![(0, 1), (1, 12), (2, 23), (4, 45), (5, 56)]);
+        assert_eq!(x.iter().count(), 0);
+        assert_eq!(x.get_hash_capacity(), 3);
+        assert!(x.get(&5).is_none());
+
+        // the table is usable after a drain, even a partial one
+        assert!(x.insert(7, 70).is_ok());
+        assert!(x.insert(8, 80).is_ok());
+        assert_eq!(x.drain().take(1).count(), 1);
+        assert!(x.get(&7).is_none() && x.get(&8).is_none());
+        assert!(x.insert(9, 90).is_ok());
+
+        let owned: Vec<(u16, u16)> = x.into_iter().collect();
+        assert_eq!(owned, vec![(9, 90)]);
+    }
+
+    #[test]
+    fn iteration_covers_stash() {
+        let mut x = CuckooHashBuilder::<u16, u16>::new()
+            .with_hash_capacity(1)
+            .with_stash_capacity(4)
+            .build();
+        // two slots outside the stash, so at least one key ends up there
+        for key in 0..5 {
+            assert!(x.insert(key, key).is_ok());
+        }
+        assert!(x.stash.iter().any(|entry| entry.data.is_some()));
+        let mut keys: Vec<u16> = x.keys().copied().collect();
+        keys.sort();
+        assert_eq!(keys, vec![0, 1, 2, 3, 4]);
+    }
+
+    #[test]
+    fn len_counts_stashed_entries() {
+        let mut x = CuckooHashBuilder::<u16, u16>::new()
+            .with_hash_capacity(1)
+            .with_stash_capacity(4)
+            .build();
+        assert_eq!(x.capacity(), 6);
+        for key in 0..5 {
+            assert!(x.insert(key, key).is_ok());
+        }
+        assert!(x.insert(2, 21).is_ok());
+        assert_eq!(x.len(), 5);
+        // two slots outside the stash, so the rest are stashed
+        assert!(x.stash_len >= 3);
+        assert_eq!(
+            x.stash_len,
+            x.stash.iter().filter(|entry| entry.data.is_some()).count()
+        );
+        for key in 0..5 {
+            assert!(x.delete(&key).is_ok());
+            assert!(x.delete(&key).is_err());
+        }
+        assert!(x.is_empty());
+        assert_eq!(x.stash_len, 0);
+
+        for key in 0..5 {
+            assert!(x.insert(key, key).is_ok());
+        }
+        x.clear();
+        assert_eq!(x.stash_len, 0);
+        assert_eq!(x.capacity(), 6);
+        assert_eq!(x.iter().count(), 0);
+    }
+
+    #[test]
+    fn hash_functions_spread_structured_keys() {
+        // keys differing only in their high bits, which masking with a
+        // random number used to collapse onto a handful of slots
+        let x = CuckooHashBuilder::<u64, u16>::new()
+            .with_hash_capacity(1024)
+            .with_hash_functions(3)
+            .with_hasher(IdentityState::default())
+            .build();
+        let mut tables = vec![vec![false; 1024]; 3];
+        for key in 0..1024u64 {
+            let hash = key << 40;
+            for (t, slots) in tables.iter_mut().enumerate() {
+                slots[x.index(t, hash)] = true;
+            }
+        }
+        // a random function covers 1 - 1/e of the slots, about 647
+        for (t, slots) in tables.iter().enumerate() {
+            let used = slots.iter().filter(|used| **used).count();
+            assert!(used > 500, "{} slots used in table {}", used, t);
+        }
+    }
+
+    #[test]
+    fn hash_functions_are_independent() {
+        // chi-squared test of the joint distribution of the slots in the
+        // first and second tables. Keys are spread over all 8 bytes by an odd
+        // multiplier, tabulation being only 3-independent over keys that
+        // differ in just a byte or two
+        let capacity = 16;
+        let per_cell = 100;
+        let x = CuckooHashBuilder::<u64, u16>::new()
+            .with_hash_capacity(capacity)
+            .with_hasher(IdentityState::default())
+            .build();
+        let mut cells = vec![0usize; capacity * capacity];
+        for key in 0..(capacity * capacity * per_cell) as u64 {
+            let hash = key.wrapping_mul(0x9e3779b97f4a7c15);
+            cells[x.index(0, hash) * capacity + x.index(1, hash)] += 1;
+        }
+        let chi_squared: f64 = cells
+            .iter()
+            .map(|count| (*count as f64 - per_cell as f64).powi(2) / per_cell as f64)
+            .sum();
+        // 255 degrees of freedom: mean 255, standard deviation about 22.6
+        assert!(
+            chi_squared < 255.0 + 6.0 * 22.6,
+            "chi squared {}",
+            chi_squared
+        );
+
+        // a key's slot in one table says nothing about its slot in the
+        // other, so keys sharing a slot in the first spread over the second
+        let colliding: Vec<u64> = (0..u64::MAX)
+            .filter(|hash| x.index(0, *hash) == 0)
+            .take(64)
+            .collect();
+        let mut secondary = vec![false; capacity];
+        for hash in colliding {
+            secondary[x.index(1, hash)] = true;
+        }
+        assert!(secondary.iter().filter(|used| **used).count() >= capacity / 2);
+    }
+
+    #[test]
+    fn rehash_rate_is_bounded() {
+        // fill tables to 40% of the slots of the two tables, well under
+        // the 50% two-table cuckoo hashing can sustain
+        let trials = 50;
+        let mut rehashes = 0;
+        for _trial in 0..trials {
+            let mut x = CuckooHashBuilder::<u64, u64>::new()
+                .with_hash_capacity(1024)
+                .with_hasher(IdentityState::default())
+                .build();
+            for key in 0..819u64 {
+                assert!(x.insert(key << 32, key).is_ok());
+            }
+            for key in 0..819u64 {
+                assert_eq!(x.get(&(key << 32)), Some(&key));
+            }
+            rehashes += x.get_rehash_count();
+        }
+        assert!(
+            rehashes <= trials / 10,
+            "{} rehashes in {} trials",
+            rehashes,
+            trials
+        );
+    }
+
+    #[test]
+    fn seeded_tables_are_deterministic() {
+        let build = |seed: u64| {
+            CuckooHashBuilder::<u16, u16>::new()
+                .with_hash_capacity(16)
+                .with_stash_capacity(0)
+                .with_seed(seed)
+                .with_hasher(SipBuildHasher::with_keys(1, 2))
+                .build()
+        };
+        let fill = |x: &mut CuckooHash<u16, u16, SipBuildHasher>| {
+            for key in 0..16 {
+                assert!(x.insert(key, key).is_ok());
+            }
+            for key in (0..16).step_by(3) {
+                assert!(x.delete(&key).is_ok());
+            }
+            for key in 100..106 {
+                assert!(x.insert(key, key).is_ok());
+            }
+        };
+        let layout = |x: &CuckooHash<u16, u16, SipBuildHasher>| -> Vec<(u16, u16)> {
+            x.iter().map(|(k, v)| (*k, *v)).collect()
+        };
+
+        // without a stash, filling half the slots takes the odd rehash
+        let mut rehashes = 0;
+        for seed in 0..20 {
+            let mut x = build(seed);
+            let mut y = build(seed);
+            fill(&mut x);
+            fill(&mut y);
+            assert_eq!(x.get_random(0), y.get_random(0));
+            assert_eq!(x.get_random(1), y.get_random(1));
+            assert_eq!(x.get_rehash_count(), y.get_rehash_count());
+            assert_eq!(layout(&x), layout(&y));
+            rehashes += x.get_rehash_count();
+        }
+        assert!(rehashes > 0);
+        assert_ne!(build(0).get_random(0), build(1).get_random(0));
+
+        // a generator handed to the builder seeds the table the same way
+        let from_rng = |seed: u64| {
+            CuckooHashBuilder::<u16, u16>::new()
+                .with_hash_capacity(64)
+                .with_rng(StdRng::seed_from_u64(seed))
+                .build()
+        };
+        assert_eq!(from_rng(3).get_random(0), from_rng(3).get_random(0));
+        assert_ne!(from_rng(3).get_random(0), from_rng(4).get_random(0));
+    }
+
+    #[test]
+    fn seed_keys_the_default_hasher() {
+        let build = |seed: u64| {
+            CuckooHashBuilder::<u16, u16>::new()
+                .with_hash_capacity(64)
+                .with_seed(seed)
+                .build()
+        };
+        let fill = |x: &mut CuckooHash<u16, u16>| {
+            for key in 0..100 {
+                assert!(x.insert(key, key).is_ok());
+            }
+        };
+        for seed in 0..10 {
+            let mut x = build(seed);
+            let mut y = build(seed);
+            fill(&mut x);
+            fill(&mut y);
+            assert_eq!(x.tables, y.tables);
+            assert_eq!(x.stash, y.stash);
+        }
+        let (mut x, mut y) = (build(0), build(1));
+        fill(&mut x);
+        fill(&mut y);
+        assert_ne!(x.tables, y.tables);
+
+        // a hasher handed to the builder after seeding is left as it is
+        let x = CuckooHashBuilder::<u16, u16>::new()
+            .with_seed(0)
+            .with_hasher(SipBuildHasher::with_keys(1, 2))
+            .build();
+        assert_eq!(
+            x.hash_builder.hash(&7u16),
+            SipBuildHasher::with_keys(1, 2).hash(&7u16)
+        );
+    }
+
+    #[test]
+    fn failed_rehash_leaves_table_intact() {
+        // four slots and no stash, so a fifth key can never be placed
+        let mut x = CuckooHashBuilder::<u16, u16>::new()
+            .with_hash_capacity(2)
+            .with_stash_capacity(0)
+            .with_max_rehash_attempts(32)
+            .build();
+        let mut inserted = Vec::new();
+        for key in 0..5 {
+            match x.insert(key, key * 10) {
+                Ok(_) => inserted.push(key),
+                Err(err) => {
+                    assert!(matches!(err, HashTableError::RehashFailed));
+                    assert!(x.get(&key).is_none());
+                }
+            }
+        }
+        assert!(inserted.len() < 5);
+        assert_eq!(x.len(), inserted.len());
+        assert_eq!(x.iter().count(), inserted.len());
+        for key in &inserted {
+            assert_eq!(x.get(key), Some(&(key * 10)));
+        }
+    }
+
+    #[test]
+    fn rehash_gives_up_after_max_attempts() {
+        // two entries fill the tables, a third cannot be placed however
+        // often the tables are rehashed
+        let mut x = CuckooHashBuilder::<u16, u16>::new()
+            .with_hash_capacity(1)
+            .with_stash_capacity(0)
+            .with_max_rehash_attempts(1000)
+            .build();
+        let mut key = 0;
+        while x.len() < 2 {
+            let _ = x.insert(key, key);
+            key += 1;
+        }
+        let rehashes = x.get_rehash_count();
+        let ret = x.insert(100, 100);
+        assert!(matches!(ret, Err(HashTableError::RehashFailed)));
+        assert_eq!(x.get_rehash_count(), rehashes + 1000);
+        assert_eq!(x.len(), 2);
+        assert!(x.get(&100).is_none());
+    }
+
+    #[test]
+    fn rehash_places_stash_entries() {
+        let mut x = CuckooHashBuilder::<u16, u16>::new()
+            .with_hash_capacity(16)
+            .with_stash_capacity(4)
+            .build();
+        for key in 0..4 {
+            x.stash[key as usize] = HashTableEntry {
+                key,
+                data: Some(Box::new(key)),
+            };
+            x.stash_len += 1;
+            x.len += 1;
+        }
+
+        // at this load every entry finds a slot outside the stash, and
+        // so does the new key the rehash leaves a slot vacant for
+        let hash = x.hash_builder.hash(&4u16);
+        let slot = x.rehash(hash, 16).unwrap();
+        assert!(matches!(slot, Slot::Table(..)));
+        assert_eq!(x.stash_len, 0);
+        VacantEntry {
+            table: &mut x,
+            key: 4,
+            slot,
+        }
+        .insert(4);
+        assert!(x.stash.iter().all(|entry| entry.data.is_none()));
+        for key in 0..5 {
+            assert_eq!(x.get(&key), Some(&key));
+        }
+        assert_eq!(x.iter().count(), 5);
+    }
+
+    #[test]
+    fn cuckoo_hash_grows_when_rehashing_fails() {
+        let mut x = CuckooHashBuilder::<u16, u16>::new()
+            .with_hash_capacity(2)
+            .with_stash_capacity(0)
+            .with_grow_after_failed_rehashes(4)
+            .build();
+        for key in 0..500 {
+            assert!(x.insert(key, key * 2).is_ok());
+        }
+        assert_eq!(x.len(), 500);
+        // with no stash, every entry has a slot in one of the tables
+        assert!(2 * x.get_hash_capacity() >= 500);
+        for key in 0..500 {
+            assert_eq!(x.get(&key), Some(&(key * 2)));
+        }
+        assert!(x.get(&500).is_none());
+    }
+
+    #[test]
+    fn grows_after_more_failed_rehashes_than_attempts() {
+        // seeded, as a single attempt is left once the tables have grown
+        // and an unlucky draw of hash functions could still fail it
+        let mut x = CuckooHashBuilder::<u16, u16>::new()
+            .with_hash_capacity(2)
+            .with_stash_capacity(0)
+            .with_max_rehash_attempts(4)
+            .with_grow_after_failed_rehashes(100)
+            .with_seed(1)
+            .build();
+        assert_eq!(x.grow_after_failed_rehashes, Some(3));
+        for key in 0..100 {
+            assert!(x.insert(key, key).is_ok());
+        }
+        assert!(2 * x.get_hash_capacity() >= 100);
+        for key in 0..100 {
+            assert_eq!(x.get(&key), Some(&key));
+        }
+    }
+
+    #[test]
+    fn cuckoo_hash_grows_from_empty() {
+        let mut x = CuckooHashBuilder::<u16, u16>::new()
+            .with_grow_after_failed_rehashes(2)
+            .with_growth_factor(1.5)
+            .build();
+        assert_eq!(x.get_hash_capacity(), 0);
+        assert!(x.get(&1).is_none());
+        assert!(x.remove(&1).is_err());
+        for key in 0..100 {
+            assert!(x.insert(key, key).is_ok());
+        }
+        assert!(x.get_hash_capacity() > 0);
+        for key in 0..100 {
+            assert_eq!(x.get(&key), Some(&key));
+        }
+    }
+
+    #[test]
+    fn cuckoo_hash_shrinks_on_low_occupancy() {
+        let mut x = CuckooHashBuilder::<u16, u16>::new()
+            .with_hash_capacity(4)
+            .with_grow_after_failed_rehashes(4)
+            .with_min_load_factor(0.1)
+            .build();
+        for key in 0..400 {
+            assert!(x.insert(key, key).is_ok());
+        }
+        let grown = x.get_hash_capacity();
+        for key in 0..390 {
+            assert!(x.delete(&key).is_ok());
+        }
+        assert!(x.get_hash_capacity() < grown);
+        assert!(x.len() <= x.get_hash_capacity());
+        for key in 0..390 {
+            assert!(x.get(&key).is_none());
+        }
+        for key in 390..400 {
+            assert_eq!(x.get(&key), Some(&key));
+        }
+        // shrinking never loses stashed entries
+        assert_eq!(x.iter().count(), 10);
+        assert_eq!(
+            x.stash_len,
+            x.stash.iter().filter(|entry| entry.data.is_some()).count()
+        );
+    }
+
+    #[test]
+    fn bucketized_cuckoo_hash_fills_past_90_percent() {
+        let mut x = CuckooHashBuilder::<u32, u32>::new()
+            .with_hash_capacity(256)
+            .with_bucket_size(4)
+            .build();
+        // 2048 slots outside the stash
+        assert_eq!(x.capacity(), 2048 + DEFAULT_STASH_SIZE);
+        let keys = 2048 * 91 / 100;
+        for key in 0..keys {
+            assert!(x.insert(key, key).is_ok(), "failed at {}", key);
+        }
+        assert_eq!(x.len(), keys as usize);
+        for key in 0..keys {
+            assert_eq!(x.get(&key), Some(&key));
+        }
+        assert!(x.delete(&7).is_ok());
+        assert!(x.get(&7).is_none());
+        assert_eq!(x.iter().count(), keys as usize - 1);
+    }
+
+    #[test]
+    fn d_ary_cuckoo_hash_fills_past_two_table_load() {
+        for (d, percent) in [(3, 85), (4, 90)] {
+            let mut x = CuckooHashBuilder::<u32, u32>::new()
+                .with_hash_capacity(512)
+                .with_hash_functions(d)
+                .build();
+            assert_eq!(x.get_hash_functions(), d);
+            assert_eq!(x.capacity(), d * 512 + DEFAULT_STASH_SIZE);
+            let keys = (d * 512 * percent / 100) as u32;
+            for key in 0..keys {
+                assert!(x.insert(key, key).is_ok(), "failed at {}", key);
+            }
+            assert_eq!(x.len(), keys as usize);
+            for key in 0..keys {
+                assert_eq!(x.get(&key), Some(&key));
+            }
+            // entries are spread over every table
+            assert!(x
+                .tables
+                .iter()
+                .all(|table| table.iter().any(|entry| entry.data.is_some())));
+            assert!(x.delete(&7).is_ok());
+            assert!(x.get(&7).is_none());
+            assert_eq!(x.iter().count(), keys as usize - 1);
+        }
+    }
+
+    #[test]
+    fn breadth_first_eviction_fills_tables() {
+        for (d, bucket_size, percent) in [(2, 4, 93), (3, 1, 88), (4, 1, 93)] {
+            let mut x = CuckooHashBuilder::<u32, u32>::new()
+                .with_hash_capacity(512)
+                .with_hash_functions(d)
+                .with_bucket_size(bucket_size)
+                .with_eviction(Eviction::BreadthFirst)
+                .build();
+            let keys = (d * 512 * bucket_size * percent / 100) as u32;
+            for key in 0..keys {
+                assert!(x.insert(key, key).is_ok(), "failed at {}", key);
+            }
+            assert_eq!(x.len(), keys as usize);
+            for key in 0..keys {
+                assert_eq!(x.get(&key), Some(&key));
+            }
+            for key in (0..keys).step_by(2) {
+                assert!(x.delete(&key).is_ok());
+            }
+            assert_eq!(x.iter().count(), keys as usize / 2);
+        }
+    }
+
+    #[test]
+    fn breadth_first_eviction_moves_nothing_on_failure() {
+        let mut x = CuckooHashBuilder::<u16, u16>::new()
+            .with_hash_capacity(2)
+            .with_stash_capacity(0)
+            .with_max_rehash_attempts(0)
+            .with_eviction(Eviction::BreadthFirst)
+            .build();
+        let layout = |x: &CuckooHash<u16, u16>| -> Vec<Option<u16>> {
+            x.tables
+                .iter()
+                .flatten()
+                .map(|entry| entry.data.as_ref().map(|_| entry.key))
+                .collect()
+        };
+        let mut failures = 0;
+        for key in 0..16 {
+            let before = layout(&x);
+            match x.insert(key, key) {
+                Ok(_) => assert_eq!(x.get(&key), Some(&key)),
+                Err(err) => {
+                    assert!(matches!(err, HashTableError::RehashFailed));
+                    assert_eq!(layout(&x), before);
+                    failures += 1;
+                }
+            }
+        }
+        // four slots, so most of the keys found no room
+        assert!(failures >= 12);
+        assert_eq!(x.len(), 16 - failures);
+    }
+
+    #[test]
+    fn breadth_first_eviction_grows_when_rehashing_fails() {
+        let mut x = CuckooHashBuilder::<u16, u16>::new()
+            .with_hash_capacity(2)
+            .with_hash_functions(3)
+            .with_stash_capacity(0)
+            .with_grow_after_failed_rehashes(4)
+            .with_eviction(Eviction::BreadthFirst)
+            .build();
+        for key in 0..500 {
+            assert!(x.insert(key, key * 2).is_ok());
+        }
+        assert!(3 * x.get_hash_capacity() >= 500);
+        for key in 0..500 {
+            assert_eq!(x.get(&key), Some(&(key * 2)));
+        }
+    }
+
+    #[test]
+    fn greedy_eviction_fills_tables() {
+        for (d, percent) in [(2, 45), (3, 80)] {
+            let mut x = CuckooHashBuilder::<u32, u32>::new()
+                .with_hash_capacity(512)
+                .with_hash_functions(d)
+                .with_eviction(Eviction::Greedy)
+                .build();
+            let keys = (d * 512 * percent / 100) as u32;
+            for key in 0..keys {
+                assert!(x.insert(key, key).is_ok(), "failed at {}", key);
+            }
+            for key in 0..keys {
+                assert_eq!(x.get(&key), Some(&key));
+            }
+            assert_eq!(x.iter().count(), keys as usize);
+        }
+    }
+
+    #[test]
+    fn max_displacements_bounds_eviction() {
+        // with no displacements allowed, an insert only ever fills a
+        // vacant slot of its own buckets or the stash
+        for eviction in [
+            Eviction::Greedy,
+            Eviction::RandomWalk,
+            Eviction::BreadthFirst,
+        ] {
+            let mut x = CuckooHashBuilder::<u16, u16>::new()
+                .with_hash_capacity(32)
+                .with_stash_capacity(64)
+                .with_max_displacements(0)
+                .with_eviction(eviction)
+                .build();
+            for key in 0..64 {
+                let before: Vec<Option<u16>> = x
+                    .tables
+                    .iter()
+                    .flatten()
+                    .map(|entry| entry.data.as_ref().map(|_| entry.key))
+                    .collect();
+                assert!(x.insert(key, key).is_ok());
+                let after = x
+                    .tables
+                    .iter()
+                    .flatten()
+                    .map(|entry| entry.data.as_ref().map(|_| entry.key));
+                for (was, is) in before.into_iter().zip(after) {
+                    assert!(was.is_none() || was == is);
+                }
+            }
+            // the key's buckets fill up, and the rest land in the stash
+            assert!(x.stash_len > 0);
+            assert_eq!(x.get_rehash_count(), 0);
+            for key in 0..64 {
+                assert_eq!(x.get(&key), Some(&key));
+            }
+        }
+    }
+
+    #[test]
+    fn stash_overflow_error_fails_with_table_full() {
+        let mut x = CuckooHashBuilder::<u16, u16>::new()
+            .with_hash_capacity(2)
+            .with_stash_capacity(1)
+            .with_stash_overflow(StashOverflow::Error)
+            .build();
+        let mut inserted = Vec::new();
+        for key in 0..8 {
+            match x.insert(key, key) {
+                Ok(_) => inserted.push(key),
+                Err(err) => assert!(matches!(err, HashTableError::TableFull)),
+            }
+        }
+        // five slots in all, and no rehash to make more room
+        assert!(inserted.len() <= 5);
+        let key = (0..8).find(|key| !inserted.contains(key)).unwrap();
+        assert!(matches!(x.entry(key), Err(HashTableError::TableFull)));
+        assert_eq!(x.get_rehash_count(), 0);
+        assert_eq!(x.get_hash_capacity(), 2);
+        assert_eq!(x.len(), inserted.len());
+        for key in 0..8 {
+            let expected = inserted.contains(&key).then_some(&key);
+            assert_eq!(x.get(&key), expected);
+        }
+    }
+
+    #[test]
+    fn unused_vacant_entry_keeps_every_entry() {
+        // a vacant entry has already displaced entries, or rehashed, to
+        // free a slot, whether or not anything is then inserted into it
+        let mut x = CuckooHashBuilder::<u16, u16>::new()
+            .with_hash_capacity(8)
+            .build();
+        for key in 0..12 {
+            assert!(matches!(x.entry(100 + key), Ok(Entry::Vacant(_))));
+            assert!(x.insert(key, key).is_ok());
+        }
+        assert_eq!(x.len(), 12);
+        assert_eq!(x.iter().count(), 12);
+        assert_eq!(
+            x.stash_len,
+            x.stash.iter().filter(|entry| entry.data.is_some()).count()
+        );
+        for key in 0..12 {
+            assert_eq!(x.get(&key), Some(&key));
+            assert!(x.get(&(100 + key)).is_none());
+        }
+    }
+
+    #[test]
+    fn stash_overflow_grow_grows_straight_away() {
+        let mut x = CuckooHashBuilder::<u16, u16>::new()
+            .with_hash_capacity(2)
+            .with_stash_capacity(0)
+            .with_stash_overflow(StashOverflow::Grow)
+            .build();
+        for key in 0..500 {
+            assert!(x.insert(key, key).is_ok());
+        }
+        assert!(2 * x.get_hash_capacity() >= 500);
+        for key in 0..500 {
+            assert_eq!(x.get(&key), Some(&key));
+        }
     }
 }
@@ -0,0 +1,57 @@
+use crate::ExtendibleHash;
+use hash_table::entry::EntryTable;
+use hash_table::slots::{self, SlotTable};
+use hash_table::{HashFn, HashTableEntry, HashTableError};
+use std::collections::hash_map::RandomState;
+use std::hash::Hash;
+
+/// a view into a single slot of an ExtendibleHash, which is either occupied
+/// by the key it was requested for or vacant and ready to take it
+pub type Entry<'a, K, U, S = RandomState> =
+    hash_table::entry::Entry<OccupiedEntry<'a, K, U, S>, VacantEntry<'a, K, U, S>>;
+
+/// the slot holding the requested key
+pub type OccupiedEntry<'a, K, U, S = RandomState> =
+    slots::OccupiedEntry<'a, ExtendibleHash<K, U, S>>;
+
+/// the empty slot the requested key will be inserted into, in the
+/// bucket the directory points it to
+pub type VacantEntry<'a, K, U, S = RandomState> = slots::VacantEntry<'a, ExtendibleHash<K, U, S>>;
+
+impl<K: Hash + Eq + Default, U, S: HashFn<K>> SlotTable for ExtendibleHash<K, U, S> {
+    type Key = K;
+    type Data = U;
+    type Vacancy = usize;
+
+    fn slots(&self) -> &[HashTableEntry<K, U>] {
+        &self.data
+    }
+
+    fn slots_mut(&mut self) -> &mut [HashTableEntry<K, U>] {
+        &mut self.data
+    }
+
+    fn place(&mut self, y: usize, key: K, data: Box<U>) -> usize {
+        ExtendibleHash::place(self, y, key, data);
+        y
+    }
+
+    fn take(&mut self, y: usize) -> Box<U> {
+        ExtendibleHash::take(self, y)
+    }
+}
+
+impl<K: Hash + Eq + Default, U, S: HashFn<K>> EntryTable<K, U> for ExtendibleHash<K, U, S> {
+    type Occupied<'a>
+        = OccupiedEntry<'a, K, U, S>
+    where
+        Self: 'a;
+    type Vacant<'a>
+        = VacantEntry<'a, K, U, S>
+    where
+        Self: 'a;
+
+    fn entry(&mut self, key: K) -> Result<Entry<'_, K, U, S>, HashTableError> {
+        ExtendibleHash::entry(self, key)
+    }
+}
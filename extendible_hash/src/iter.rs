@@ -0,0 +1,67 @@
+use crate::ExtendibleHash;
+use hash_table::{iter, HashFn};
+use std::hash::Hash;
+
+pub use hash_table::slots::{IntoIter, Iter, IterMut};
+
+pub type Keys<'a, K, U> = iter::Keys<Iter<'a, K, U>>;
+
+pub type Values<'a, K, U> = iter::Values<Iter<'a, K, U>>;
+
+/// iterator over the entries removed from an ExtendibleHash by drain. The
+/// table is empty as soon as drain returns, whether or not this is
+/// run to completion
+pub type Drain<'a, K, U> = iter::Drain<'a, IntoIter<K, U>>;
+
+impl<K, U, S> ExtendibleHash<K, U, S> {
+    pub fn iter(&self) -> Iter<'_, K, U> {
+        Iter::new(&self.data)
+    }
+
+    pub fn iter_mut(&mut self) -> IterMut<'_, K, U> {
+        IterMut::new(&mut self.data)
+    }
+
+    pub fn keys(&self) -> Keys<'_, K, U> {
+        Keys::new(self.iter())
+    }
+
+    pub fn values(&self) -> Values<'_, K, U> {
+        Values::new(self.iter())
+    }
+}
+
+impl<K: Hash + Eq + Default, U, S: HashFn<K>> ExtendibleHash<K, U, S> {
+    /// remove every entry, keeping the directory and buckets
+    pub fn drain(&mut self) -> Drain<'_, K, U> {
+        let slots = self.reset();
+        Drain::new(IntoIter::new(slots))
+    }
+}
+
+impl<K, U, S> IntoIterator for ExtendibleHash<K, U, S> {
+    type Item = (K, U);
+    type IntoIter = IntoIter<K, U>;
+
+    fn into_iter(self) -> IntoIter<K, U> {
+        IntoIter::new(self.data)
+    }
+}
+
+impl<'a, K, U, S> IntoIterator for &'a ExtendibleHash<K, U, S> {
+    type Item = (&'a K, &'a U);
+    type IntoIter = Iter<'a, K, U>;
+
+    fn into_iter(self) -> Iter<'a, K, U> {
+        self.iter()
+    }
+}
+
+impl<'a, K, U, S> IntoIterator for &'a mut ExtendibleHash<K, U, S> {
+    type Item = (&'a K, &'a mut U);
+    type IntoIter = IterMut<'a, K, U>;
+
+    fn into_iter(self) -> IterMut<'a, K, U> {
+        self.iter_mut()
+    }
+}
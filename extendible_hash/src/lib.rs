@@ -0,0 +1,576 @@
+#![allow(dead_code)]
+
+use hash_table::{HashFn, HashTable, HashTableEntry, HashTableError};
+use std::collections::hash_map::RandomState;
+use std::hash::Hash;
+use std::marker::PhantomData;
+use std::mem;
+use std::ops::Range;
+
+mod entry;
+mod iter;
+
+pub use entry::{Entry, OccupiedEntry, VacantEntry};
+pub use hash_table::entry::{Occupied, Vacant};
+pub use iter::{Drain, IntoIter, Iter, IterMut, Keys, Values};
+
+/// bytes in a page, for sizing buckets with with_page_size
+pub const PAGE_SIZE: usize = 4096;
+
+const DEFAULT_BUCKET_SIZE: usize = 4;
+const DEFAULT_MAX_GLOBAL_DEPTH: u32 = 20;
+const MAX_GLOBAL_DEPTH: u32 = 32;
+
+/// a fixed number of slots holding the entries whose hashes share the
+/// low local_depth bits. The slots themselves are kept by the table
+struct Bucket {
+    local_depth: u32,
+}
+
+/// a table of fixed size buckets found through a directory indexed by
+/// the low global_depth bits of a key's hash. A full bucket is split in
+/// two by one more bit of hash, doubling the directory only when the
+/// bucket already used every bit the directory does. Other buckets are
+/// left alone, each pointed to by as many directory slots as the bits
+/// it does not use allow
+pub struct ExtendibleHash<K, U, S = RandomState> {
+    // bucket index for every combination of the low global_depth bits
+    directory: Vec<usize>,
+    buckets: Vec<Bucket>,
+    // the slots of every bucket, bucket_size of them each, in the order
+    // the buckets were created
+    data: Vec<HashTableEntry<K, U>>,
+    global_depth: u32,
+    // slots in every bucket
+    bucket_size: usize,
+    // inserts fail with TableFull rather than double the directory
+    // past this depth
+    max_global_depth: u32,
+    // number of slots holding an entry
+    len: usize,
+    // hashes keys, the low bits of the hash pick the directory slot
+    hash_builder: S,
+}
+
+impl<K, U, S> ExtendibleHash<K, U, S> {
+    fn get_capacity(&self) -> usize {
+        self.data.len()
+    }
+
+    /// the slots of bucket
+    fn bucket_slots(&self, bucket: usize) -> Range<usize> {
+        bucket * self.bucket_size..(bucket + 1) * self.bucket_size
+    }
+
+    /// the directory slot for a hash
+    fn index(&self, hash: u64) -> usize {
+        (hash & ((1 << self.global_depth) - 1)) as usize
+    }
+}
+
+impl<K: Hash + Eq + Default, U, S: HashFn<K>> ExtendibleHash<K, U, S> {
+    /// get the entry for key for in-place manipulation. Room for the
+    /// key is made up front by splitting its bucket until it has a free
+    /// slot, so this fails with TableFull if the key is absent and that
+    /// would take the directory past its maximum depth
+    pub fn entry(&mut self, key: K) -> Result<Entry<'_, K, U, S>, HashTableError> {
+        let hash = self.hash(&key);
+        loop {
+            let bucket = self.directory[self.index(hash)];
+            match self.probe(bucket, &key) {
+                Ok(slot) => return Ok(Entry::Occupied(OccupiedEntry::new(self, slot))),
+                Err(Some(slot)) => return Ok(Entry::Vacant(VacantEntry::new(self, key, slot))),
+                Err(None) => self.split(bucket, hash)?,
+            }
+        }
+    }
+}
+
+impl<K: Hash + Eq + Default, U, S: HashFn<K>> ExtendibleHash<K, U, S> {
+    /// the slot holding key
+    fn find_slot(&self, key: &K) -> Option<usize> {
+        let bucket = self.directory[self.index(self.hash(key))];
+        self.probe(bucket, key).ok()
+    }
+
+    /// look through the slots of bucket for key, returning Ok with the
+    /// slot holding it, or Err with the first free slot, if any
+    fn probe(&self, bucket: usize, key: &K) -> Result<usize, Option<usize>> {
+        let mut free = None;
+        for slot in self.bucket_slots(bucket) {
+            let entry = &self.data[slot];
+            if entry.data.is_none() {
+                free = free.or(Some(slot));
+            } else if entry.key == *key {
+                return Ok(slot);
+            }
+        }
+        Err(free)
+    }
+
+    fn place(&mut self, slot: usize, key: K, data: Box<U>) {
+        let entry = &mut self.data[slot];
+        entry.key = key;
+        entry.data = Some(data);
+        self.len += 1;
+    }
+
+    /// empty the occupied slot and return its data. Buckets are never
+    /// merged back, the table keeps the buckets it has split
+    fn take(&mut self, slot: usize) -> Box<U> {
+        let data = self.data[slot].data.take().unwrap();
+        self.len -= 1;
+        data
+    }
+
+    /// split the full bucket that hash addresses by the next bit of
+    /// hash, doubling the directory first if the bucket already uses
+    /// every bit it does. Entries with the bit set move to a new bucket,
+    /// which takes over the directory slots with the bit set
+    fn split(&mut self, bucket: usize, hash: u64) -> Result<(), HashTableError> {
+        let depth = self.buckets[bucket].local_depth;
+        if depth == self.global_depth {
+            if self.global_depth == self.max_global_depth {
+                return Err(HashTableError::TableFull);
+            }
+            // the new upper half of the directory mirrors the lower half
+            self.directory.extend_from_within(..);
+            self.global_depth += 1;
+        }
+
+        let new_bucket = self.buckets.len();
+        self.buckets.push(Bucket {
+            local_depth: depth + 1,
+        });
+        self.data
+            .resize_with(self.data.len() + self.bucket_size, Default::default);
+        self.buckets[bucket].local_depth = depth + 1;
+        let low_bits = hash as usize & ((1 << depth) - 1);
+        for y in (low_bits | (1 << depth)..self.directory.len()).step_by(1 << (depth + 1)) {
+            self.directory[y] = new_bucket;
+        }
+
+        let slots = self.bucket_slots(bucket);
+        let old: Vec<_> = self.data[slots].iter_mut().map(mem::take).collect();
+        for entry in old {
+            if let Some(data) = entry.data {
+                let hash = self.hash(&entry.key);
+                let target = if hash >> depth & 1 == 1 {
+                    new_bucket
+                } else {
+                    bucket
+                };
+                // the two buckets have as many slots as the one split
+                let slots = self.bucket_slots(target);
+                let slot = self.data[slots]
+                    .iter_mut()
+                    .find(|slot| slot.data.is_none())
+                    .unwrap();
+                slot.key = entry.key;
+                slot.data = Some(data);
+            }
+        }
+        Ok(())
+    }
+
+    /// empty every bucket, keeping the directory and the buckets it
+    /// points to, and hand back the old slots
+    fn reset(&mut self) -> Vec<HashTableEntry<K, U>> {
+        let mut data = Vec::with_capacity(self.get_capacity());
+        data.resize_with(self.get_capacity(), Default::default);
+        self.len = 0;
+        mem::replace(&mut self.data, data)
+    }
+}
+
+pub struct ExtendibleHashBuilder<K, U, S = RandomState> {
+    _phantom_k: PhantomData<K>,
+    _phantom_u: PhantomData<U>,
+    capacity: usize,
+    bucket_size: usize,
+    max_global_depth: u32,
+    hash_builder: S,
+}
+
+impl<K: Default, U> ExtendibleHashBuilder<K, U> {
+    pub fn new() -> ExtendibleHashBuilder<K, U> {
+        ExtendibleHashBuilder::<K, U> {
+            _phantom_k: PhantomData {},
+            _phantom_u: PhantomData {},
+            capacity: 0,
+            bucket_size: DEFAULT_BUCKET_SIZE,
+            max_global_depth: DEFAULT_MAX_GLOBAL_DEPTH,
+            hash_builder: RandomState::new(),
+        }
+    }
+}
+
+impl<K: Default, U> Default for ExtendibleHashBuilder<K, U> {
+    fn default() -> ExtendibleHashBuilder<K, U> {
+        ExtendibleHashBuilder::new()
+    }
+}
+
+impl<K: Default, U, S> ExtendibleHashBuilder<K, U, S> {
+    /// number of slots to start with, rounded up to a power of two
+    /// number of buckets
+    pub fn with_capacity(mut self, capacity: usize) -> ExtendibleHashBuilder<K, U, S> {
+        self.capacity = capacity;
+        self
+    }
+
+    /// number of slots in a bucket, a bucket splits once they are all
+    /// full
+    pub fn with_bucket_size(mut self, bucket_size: usize) -> ExtendibleHashBuilder<K, U, S> {
+        assert!(bucket_size > 0, "bucket size must be at least 1");
+        self.bucket_size = bucket_size;
+        self
+    }
+
+    /// size buckets to as many entries as fit in page_size bytes, such
+    /// as PAGE_SIZE. An entry is its key plus a pointer to its boxed
+    /// data, the data itself lives outside the bucket
+    pub fn with_page_size(self, page_size: usize) -> ExtendibleHashBuilder<K, U, S> {
+        let entry_size = mem::size_of::<HashTableEntry<K, U>>().max(1);
+        self.with_bucket_size((page_size / entry_size).max(1))
+    }
+
+    /// the deepest the directory may grow, at 2^depth slots. Inserts
+    /// that would need it any deeper fail with TableFull
+    pub fn with_max_global_depth(mut self, depth: u32) -> ExtendibleHashBuilder<K, U, S> {
+        assert!(
+            depth <= MAX_GLOBAL_DEPTH,
+            "max global depth must be at most 32"
+        );
+        self.max_global_depth = depth;
+        self
+    }
+
+    /// hash keys with hash_builder rather than a randomly keyed SipHash.
+    /// Any BuildHasher will do, as will the hashers in hash_table::hashers
+    pub fn with_hasher<T>(self, hash_builder: T) -> ExtendibleHashBuilder<K, U, T> {
+        ExtendibleHashBuilder::<K, U, T> {
+            _phantom_k: PhantomData {},
+            _phantom_u: PhantomData {},
+            capacity: self.capacity,
+            bucket_size: self.bucket_size,
+            max_global_depth: self.max_global_depth,
+            hash_builder,
+        }
+    }
+
+    pub fn build(self) -> ExtendibleHash<K, U, S> {
+        let buckets = self.capacity.div_ceil(self.bucket_size).next_power_of_two();
+        let global_depth = buckets.trailing_zeros().min(self.max_global_depth);
+        let mut hash = ExtendibleHash::<K, U, S> {
+            directory: (0..1 << global_depth).collect(),
+            buckets: Vec::with_capacity(1 << global_depth),
+            data: Vec::with_capacity(self.bucket_size << global_depth),
+            global_depth,
+            bucket_size: self.bucket_size,
+            max_global_depth: self.max_global_depth,
+            len: 0,
+            hash_builder: self.hash_builder,
+        };
+
+        // initialize the hash table
+        for _i in 0..1 << global_depth {
+            hash.buckets.push(Bucket {
+                local_depth: global_depth,
+            });
+        }
+        hash.data
+            .resize_with(self.bucket_size << global_depth, Default::default);
+        hash
+    }
+}
+
+impl<K: Hash + Eq + Default, U, S: HashFn<K>> HashTable<K, U> for ExtendibleHash<K, U, S> {
+    fn insert(&mut self, key: K, data: U) -> Result<Option<U>, HashTableError> {
+        match self.entry(key)? {
+            Entry::Occupied(mut entry) => Ok(Some(entry.insert(data))),
+            Entry::Vacant(entry) => {
+                entry.insert(data);
+                Ok(None)
+            }
+        }
+    }
+
+    fn remove(&mut self, key: &K) -> Result<U, HashTableError> {
+        let slot = self.find_slot(key).ok_or(HashTableError::NotFound)?;
+        Ok(*self.take(slot))
+    }
+
+    fn get(&self, key: &K) -> Option<&U> {
+        let slot = self.find_slot(key)?;
+        self.data[slot].data.as_deref()
+    }
+
+    fn get_mut(&mut self, key: &K) -> Option<&mut U> {
+        let slot = self.find_slot(key)?;
+        self.data[slot].data.as_deref_mut()
+    }
+
+    fn len(&self) -> usize {
+        self.len
+    }
+
+    fn clear(&mut self) {
+        self.reset();
+    }
+
+    fn capacity(&self) -> usize {
+        self.get_capacity()
+    }
+}
+
+impl<K, U, S: HashFn<K>> ExtendibleHash<K, U, S> {
+    fn hash(&self, key: &K) -> u64 {
+        self.hash_builder.hash(key)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use hash_table::hashers::FnvBuildHasher;
+    use hash_table::testing::{check_table, TableBuilder};
+
+    struct Builder;
+
+    impl TableBuilder for Builder {
+        type Table<K: Hash + Eq + Default, U, S: HashFn<K>> = ExtendibleHash<K, U, S>;
+
+        fn build<K: Hash + Eq + Default, U, S: HashFn<K>>(
+            &self,
+            hash_builder: S,
+        ) -> ExtendibleHash<K, U, S> {
+            ExtendibleHashBuilder::new()
+                .with_bucket_size(2)
+                .with_hasher(hash_builder)
+                .build()
+        }
+    }
+
+    #[test]
+    fn passes_table_tests() {
+        check_table(Builder);
+    }
+
+    // the directory has 2^global_depth slots, and every bucket is
+    // pointed to by exactly the slots sharing its low local_depth bits,
+    // which are the low bits of the hash of every key it holds
+    fn check_directory<S: HashFn<u16>>(x: &ExtendibleHash<u16, u16, S>) {
+        assert_eq!(x.directory.len(), 1 << x.global_depth);
+        let mut pointers = vec![0; x.buckets.len()];
+        for (y, bucket) in x.directory.iter().enumerate() {
+            pointers[*bucket] += 1;
+            let mask = (1 << x.buckets[*bucket].local_depth) - 1;
+            assert_eq!(x.directory[y & mask], *bucket);
+        }
+        assert_eq!(x.data.len(), x.buckets.len() * x.bucket_size);
+        let mut len = 0;
+        for (b, bucket) in x.buckets.iter().enumerate() {
+            assert!(bucket.local_depth <= x.global_depth);
+            assert_eq!(pointers[b], 1 << (x.global_depth - bucket.local_depth));
+            for entry in x.data[x.bucket_slots(b)]
+                .iter()
+                .filter(|entry| entry.data.is_some())
+            {
+                assert_eq!(x.directory[x.index(x.hash(&entry.key))], b);
+                len += 1;
+            }
+        }
+        assert_eq!(len, x.len);
+    }
+
+    #[test]
+    fn splits_only_overflowing_buckets() {
+        let mut x = ExtendibleHashBuilder::<u16, u16>::new()
+            .with_bucket_size(2)
+            .build();
+        assert_eq!(x.global_depth, 0);
+        for key in 0..1000 {
+            let buckets = x.buckets.len();
+            let full = (0..buckets).filter(|&bucket| {
+                x.data[x.bucket_slots(bucket)]
+                    .iter()
+                    .all(|entry| entry.data.is_some())
+            });
+            let full = full.count();
+            assert!(x.insert(key, key * 10).is_ok());
+            // only the full bucket the key hashed to can have split, maybe
+            // more than once if its entries all went the same way
+            assert!(x.buckets.len() == buckets || full > 0);
+            check_directory(&x);
+        }
+        assert_eq!(x.len, 1000);
+        assert!(x.get_capacity() >= 1000);
+        // buckets are split one at a time, the directory doubles
+        assert!(x.buckets.len() <= x.directory.len());
+        assert!(x
+            .buckets
+            .iter()
+            .any(|bucket| bucket.local_depth < x.global_depth));
+        for key in 0..1000 {
+            assert_eq!(x.get(&key), Some(&(key * 10)));
+        }
+        for key in (0..1000).step_by(2) {
+            assert!(x.delete(&key).is_ok());
+        }
+        for key in 0..1000 {
+            assert_eq!(x.get(&key).is_some(), key % 2 == 1);
+        }
+        check_directory(&x);
+    }
+
+    // a key for each of the four combinations of the low two hash bits
+    fn keys_by_low_bits<S: HashFn<u16>>(x: &ExtendibleHash<u16, u16, S>) -> Vec<u16> {
+        (0..4)
+            .map(|bits| {
+                (0..u16::MAX)
+                    .find(|key| x.hash(key) & 0b11 == bits)
+                    .unwrap()
+            })
+            .collect()
+    }
+
+    #[test]
+    fn depths_follow_splits() {
+        let mut x = ExtendibleHashBuilder::<u16, u16>::new()
+            .with_bucket_size(1)
+            .with_hasher(FnvBuildHasher)
+            .build();
+        let keys = keys_by_low_bits(&x);
+        let depths = |x: &ExtendibleHash<u16, u16, FnvBuildHasher>| {
+            let local: Vec<u32> = x.buckets.iter().map(|bucket| bucket.local_depth).collect();
+            (x.global_depth, local)
+        };
+        assert!(x.insert(keys[0b00], 0).is_ok());
+        assert_eq!(depths(&x), (0, vec![0]));
+
+        // the only bucket splits on bit 0, doubling the directory
+        assert!(x.insert(keys[0b01], 1).is_ok());
+        assert_eq!(depths(&x), (1, vec![1, 1]));
+
+        // the bucket for bit 0 set splits on bit 1, doubling the
+        // directory again. The other bucket keeps its depth
+        assert!(x.insert(keys[0b11], 3).is_ok());
+        assert_eq!(depths(&x), (2, vec![1, 2, 2]));
+
+        // a bucket below the global depth splits without doubling
+        assert!(x.insert(keys[0b10], 2).is_ok());
+        assert_eq!(depths(&x), (2, vec![2, 2, 2, 2]));
+        check_directory(&x);
+    }
+
+    #[test]
+    fn directory_aliases_unsplit_buckets() {
+        let mut x = ExtendibleHashBuilder::<u16, u16>::new()
+            .with_bucket_size(1)
+            .with_hasher(FnvBuildHasher)
+            .build();
+        let keys = keys_by_low_bits(&x);
+        for bits in [0b00, 0b01, 0b11] {
+            assert!(x.insert(keys[bits], bits as u16).is_ok());
+        }
+        // slots 00 and 10 share the bucket that has only split on bit 0
+        assert_eq!(x.directory, vec![0, 1, 0, 2]);
+        check_directory(&x);
+        let bucket = |key: &u16| x.directory[x.index(x.hash(key))];
+        assert_eq!(bucket(&keys[0b00]), bucket(&keys[0b10]));
+        assert_ne!(bucket(&keys[0b01]), bucket(&keys[0b11]));
+
+        // a key for the aliased slot finds its bucket full, and the
+        // split gives the slot a bucket of its own
+        assert!(x.insert(keys[0b10], 2).is_ok());
+        assert_eq!(x.directory, vec![0, 1, 3, 2]);
+        check_directory(&x);
+        for (bits, key) in keys.iter().enumerate() {
+            assert_eq!(x.get(key), Some(&(bits as u16)));
+        }
+    }
+
+    #[test]
+    fn split_doubles_directory_only_when_needed() {
+        let mut x = ExtendibleHashBuilder::<u16, u16>::new()
+            .with_bucket_size(1)
+            .with_hasher(FnvBuildHasher)
+            .build();
+        // two keys whose hashes differ first in bit 2 need a directory
+        // of depth 3 to tell apart
+        let first = 0;
+        let low_bits = |key: &u16| x.hash(key) & 0b111;
+        let second = (1..u16::MAX)
+            .find(|key| low_bits(key) == low_bits(&first) ^ 0b100)
+            .unwrap();
+        assert!(x.insert(first, 0).is_ok());
+        assert!(x.insert(second, 1).is_ok());
+        assert_eq!(x.global_depth, 3);
+        // one bucket per split, buckets for the other bit patterns are
+        // shared rather than created
+        assert_eq!(x.buckets.len(), 4);
+        check_directory(&x);
+
+        // a key landing in a shared bucket with room splits nothing
+        let third = (1..u16::MAX)
+            .find(|key| {
+                let bucket = x.directory[x.index(x.hash(key))];
+                x.data[x.bucket_slots(bucket).start].data.is_none()
+            })
+            .unwrap();
+        assert!(x.insert(third, 2).is_ok());
+        assert_eq!(x.global_depth, 3);
+        assert_eq!(x.buckets.len(), 4);
+        check_directory(&x);
+    }
+
+    #[test]
+    fn insert_fails_past_max_global_depth() {
+        let mut x = ExtendibleHashBuilder::<u16, u16>::new()
+            .with_bucket_size(1)
+            .with_max_global_depth(2)
+            .build();
+        let mut inserted = Vec::new();
+        for key in 0..100 {
+            match x.insert(key, key) {
+                Ok(_) => inserted.push(key),
+                Err(err) => assert!(matches!(err, HashTableError::TableFull)),
+            }
+            assert!(x.global_depth <= 2);
+            check_directory(&x);
+        }
+        assert_eq!(x.len(), inserted.len());
+        assert_eq!(inserted.len(), 4);
+        // the table is full, so only existing keys have entries
+        assert!(matches!(x.entry(100), Err(HashTableError::TableFull)));
+        assert!(x.entry(inserted[0]).is_ok());
+        for key in inserted {
+            assert_eq!(x.get(&key), Some(&key));
+        }
+    }
+
+    #[test]
+    fn can_size_buckets_to_pages() {
+        let x = ExtendibleHashBuilder::<u64, u64>::new()
+            .with_page_size(PAGE_SIZE)
+            .build();
+        let entry_size = mem::size_of::<HashTableEntry<u64, u64>>();
+        assert_eq!(x.bucket_size, PAGE_SIZE / entry_size);
+        assert!(x.bucket_size * entry_size <= PAGE_SIZE);
+
+        // entries larger than a page still get a slot
+        let x = ExtendibleHashBuilder::<u64, u64>::new()
+            .with_page_size(entry_size - 1)
+            .build();
+        assert_eq!(x.bucket_size, 1);
+
+        // capacity is rounded up to a power of two number of buckets
+        let x = ExtendibleHashBuilder::<u64, u64>::new()
+            .with_bucket_size(4)
+            .with_capacity(100)
+            .build();
+        assert_eq!(x.global_depth, 5);
+        assert_eq!(x.capacity(), 128);
+    }
+}
@@ -0,0 +1,240 @@
+// Chains, iterators and entries for tables that keep their entries in a
+// vector of buckets, each the head of a singly linked chain of nodes, as
+// the chained tables do. An entry holds the link that points, or will
+// point, at the node for its key, and the table's count of entries to
+// keep up to date.
+//
+// below inspired by https://rust-unofficial.github.io/too-many-lists/first-final.html
+// iterator impl is original code after some trial and error
+
+use crate::entry::{Occupied, Vacant};
+use crate::HashTableEntry;
+
+pub type Link<K, U> = Option<Box<ChainEntry<K, U>>>;
+
+/// a bucket, the chain of nodes whose keys hash to it
+#[derive(Debug, PartialEq)]
+pub struct List<K, U> {
+    pub head: Link<K, U>,
+}
+
+/// a node of a chain
+#[derive(Debug, PartialEq)]
+pub struct ChainEntry<K, U> {
+    pub data: HashTableEntry<K, U>,
+    pub next: Link<K, U>,
+}
+
+/// iterator over the links of a chain that point at a node
+#[derive(Debug)]
+pub struct ChainIterator<'a, K, U> {
+    next: &'a Link<K, U>,
+}
+
+impl<'a, K, U> Iterator for ChainIterator<'a, K, U> {
+    type Item = &'a Link<K, U>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let current = self.next;
+
+        match current {
+            None => {
+                self.next = &None;
+                None
+            }
+            Some(node) => {
+                self.next = &node.next;
+                Some(current)
+            }
+        }
+    }
+}
+
+impl<K, U> List<K, U> {
+    pub fn iter(&self) -> ChainIterator<'_, K, U> {
+        ChainIterator { next: &self.head }
+    }
+}
+
+/// iterator over the key and data of every node, bucket by bucket and
+/// down each chain
+pub struct Iter<'a, K, U> {
+    buckets: std::slice::Iter<'a, List<K, U>>,
+    node: Option<&'a ChainEntry<K, U>>,
+}
+
+/// iterator over the key and mutable data of every node
+pub struct IterMut<'a, K, U> {
+    buckets: std::slice::IterMut<'a, List<K, U>>,
+    node: Option<&'a mut ChainEntry<K, U>>,
+}
+
+/// owning iterator over the entries of every node, unlinking one node
+/// at a time
+pub struct IntoIter<K, U> {
+    buckets: std::vec::IntoIter<List<K, U>>,
+    link: Link<K, U>,
+}
+
+impl<'a, K, U> Iter<'a, K, U> {
+    pub fn new(buckets: &'a [List<K, U>]) -> Iter<'a, K, U> {
+        Iter {
+            buckets: buckets.iter(),
+            node: None,
+        }
+    }
+}
+
+impl<'a, K, U> IterMut<'a, K, U> {
+    pub fn new(buckets: &'a mut [List<K, U>]) -> IterMut<'a, K, U> {
+        IterMut {
+            buckets: buckets.iter_mut(),
+            node: None,
+        }
+    }
+}
+
+impl<K, U> IntoIter<K, U> {
+    pub fn new(buckets: Vec<List<K, U>>) -> IntoIter<K, U> {
+        IntoIter {
+            buckets: buckets.into_iter(),
+            link: None,
+        }
+    }
+}
+
+impl<'a, K, U> Iterator for Iter<'a, K, U> {
+    type Item = (&'a K, &'a U);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            if let Some(node) = self.node {
+                self.node = node.next.as_deref();
+                return Some((&node.data.key, node.data.data.as_deref().unwrap()));
+            }
+            self.node = self.buckets.next()?.head.as_deref();
+        }
+    }
+}
+
+impl<'a, K, U> Iterator for IterMut<'a, K, U> {
+    type Item = (&'a K, &'a mut U);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            if let Some(node) = self.node.take() {
+                let ChainEntry { data, next } = node;
+                self.node = next.as_deref_mut();
+                return Some((&data.key, data.data.as_deref_mut().unwrap()));
+            }
+            self.node = self.buckets.next()?.head.as_deref_mut();
+        }
+    }
+}
+
+impl<K, U> Iterator for IntoIter<K, U> {
+    type Item = (K, U);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            if let Some(mut node) = self.link.take() {
+                self.link = node.next.take();
+                return Some((node.data.key, *node.data.data.unwrap()));
+            }
+            self.link = self.buckets.next()?.head;
+        }
+    }
+}
+
+/// the link pointing at the node holding the requested key
+pub struct OccupiedEntry<'a, K, U> {
+    link: &'a mut Link<K, U>,
+    len: &'a mut usize,
+}
+
+/// the empty link at the end of the chain the requested key hashes to
+pub struct VacantEntry<'a, K, U> {
+    link: &'a mut Link<K, U>,
+    len: &'a mut usize,
+    key: K,
+}
+
+impl<'a, K, U> OccupiedEntry<'a, K, U> {
+    pub fn new(link: &'a mut Link<K, U>, len: &'a mut usize) -> OccupiedEntry<'a, K, U> {
+        OccupiedEntry { link, len }
+    }
+}
+
+impl<'a, K, U> VacantEntry<'a, K, U> {
+    pub fn new(link: &'a mut Link<K, U>, len: &'a mut usize, key: K) -> VacantEntry<'a, K, U> {
+        VacantEntry { link, len, key }
+    }
+}
+
+impl<'a, K, U> Occupied<'a> for OccupiedEntry<'a, K, U> {
+    type Key = K;
+    type Data = U;
+
+    fn key(&self) -> &K {
+        &self.link.as_ref().unwrap().data.key
+    }
+
+    fn get(&self) -> &U {
+        self.link.as_ref().unwrap().data.data.as_deref().unwrap()
+    }
+
+    fn get_mut(&mut self) -> &mut U {
+        self.link
+            .as_mut()
+            .unwrap()
+            .data
+            .data
+            .as_deref_mut()
+            .unwrap()
+    }
+
+    fn into_mut(self) -> &'a mut U {
+        self.link
+            .as_mut()
+            .unwrap()
+            .data
+            .data
+            .as_deref_mut()
+            .unwrap()
+    }
+
+    /// unlink the node from its chain, returning its data
+    fn remove(self) -> U {
+        let mut node = self.link.take().unwrap();
+        *self.link = node.next.take();
+        *self.len -= 1;
+        *node.data.data.unwrap()
+    }
+}
+
+impl<'a, K, U> Vacant<'a> for VacantEntry<'a, K, U> {
+    type Key = K;
+    type Data = U;
+
+    fn key(&self) -> &K {
+        &self.key
+    }
+
+    fn into_key(self) -> K {
+        self.key
+    }
+
+    /// append a node holding data under the key to the chain
+    fn insert(self, data: U) -> &'a mut U {
+        let entry = ChainEntry::<K, U> {
+            data: HashTableEntry::<K, U> {
+                key: self.key,
+                data: Some(Box::new(data)),
+            },
+            next: None,
+        };
+        *self.len += 1;
+        let node = self.link.insert(Box::new(entry));
+        node.data.data.as_deref_mut().unwrap()
+    }
+}
@@ -0,0 +1,113 @@
+// The entry API every table shares. A table hands out an Entry for a
+// single key, which holds either an Occupied view of the entry for the
+// key or a Vacant view of where the key will go. The tables supply the
+// two views, the slots and chain modules have ones to reuse, and the
+// combinators on Entry work the same whatever the table.
+
+use crate::HashTableError;
+
+/// a view into a table for a single key, which is either occupied by
+/// the key or vacant and ready to take it
+pub enum Entry<O, V> {
+    Occupied(O),
+    Vacant(V),
+}
+
+/// the entry holding the requested key
+pub trait Occupied<'a>: Sized {
+    type Key;
+    type Data: 'a;
+
+    fn key(&self) -> &Self::Key;
+
+    fn get(&self) -> &Self::Data;
+
+    fn get_mut(&mut self) -> &mut Self::Data;
+
+    /// a mutable reference to the data that outlives the entry
+    fn into_mut(self) -> &'a mut Self::Data;
+
+    /// replace the data in the entry, returning the old data
+    fn insert(&mut self, data: Self::Data) -> Self::Data {
+        std::mem::replace(self.get_mut(), data)
+    }
+
+    /// remove the entry from the table, returning its data
+    fn remove(self) -> Self::Data;
+}
+
+/// the place the requested key will be inserted into
+pub trait Vacant<'a>: Sized {
+    type Key;
+    type Data: 'a;
+
+    fn key(&self) -> &Self::Key;
+
+    fn into_key(self) -> Self::Key;
+
+    /// insert data under the key, returning a mutable reference to it
+    fn insert(self, data: Self::Data) -> &'a mut Self::Data;
+}
+
+impl<'a, O, V> Entry<O, V>
+where
+    O: Occupied<'a>,
+    V: Vacant<'a, Key = O::Key, Data = O::Data>,
+{
+    pub fn key(&self) -> &O::Key {
+        match self {
+            Entry::Occupied(entry) => entry.key(),
+            Entry::Vacant(entry) => entry.key(),
+        }
+    }
+
+    /// insert data if the entry is vacant, then return a mutable
+    /// reference to the data in the entry
+    pub fn or_insert(self, data: O::Data) -> &'a mut O::Data {
+        match self {
+            Entry::Occupied(entry) => entry.into_mut(),
+            Entry::Vacant(entry) => entry.insert(data),
+        }
+    }
+
+    /// as or_insert, only calling f to create the data if the entry is
+    /// vacant
+    pub fn or_insert_with<F: FnOnce() -> O::Data>(self, f: F) -> &'a mut O::Data {
+        match self {
+            Entry::Occupied(entry) => entry.into_mut(),
+            Entry::Vacant(entry) => entry.insert(f()),
+        }
+    }
+
+    pub fn or_default(self) -> &'a mut O::Data
+    where
+        O::Data: Default,
+    {
+        self.or_insert_with(O::Data::default)
+    }
+
+    /// call f on the data if the entry is occupied
+    pub fn and_modify<F: FnOnce(&mut O::Data)>(mut self, f: F) -> Self {
+        if let Entry::Occupied(entry) = &mut self {
+            f(entry.get_mut());
+        }
+        self
+    }
+}
+
+/// a table with an entry API, for code that works with any table
+pub trait EntryTable<K, U> {
+    type Occupied<'a>: Occupied<'a, Key = K, Data = U>
+    where
+        Self: 'a;
+    type Vacant<'a>: Vacant<'a, Key = K, Data = U>
+    where
+        Self: 'a;
+
+    /// get the entry for key for in-place manipulation. Fails if the key
+    /// is absent and the table cannot make room for it
+    fn entry(
+        &mut self,
+        key: K,
+    ) -> Result<Entry<Self::Occupied<'_>, Self::Vacant<'_>>, HashTableError>;
+}
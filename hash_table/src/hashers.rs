@@ -0,0 +1,552 @@
+// hash functions that can be handed to any table builder's with_hasher.
+//
+// FnvBuildHasher, SipBuildHasher and XxBuildHasher are BuildHashers, so
+// they also work with std's HashMap. MultiplyShift and Tabulation are
+// universal families: each value is one member of the family, drawn at
+// random by new() or reproducibly by with_seed(). Both hash a single
+// 64-bit word directly with hash_u64, and fold longer keys through the
+// same function one word at a time.
+
+use crate::HashFn;
+use std::collections::hash_map::RandomState;
+use std::hash::{BuildHasher, Hash, Hasher};
+
+const FNV_OFFSET_BASIS: u64 = 0xcbf29ce484222325;
+const FNV_PRIME: u64 = 0x100000001b3;
+
+const XX_PRIME_1: u64 = 0x9e3779b185ebca87;
+const XX_PRIME_2: u64 = 0xc2b2ae3d27d4eb4f;
+const XX_PRIME_3: u64 = 0x165667b19e3779f9;
+const XX_PRIME_4: u64 = 0x85ebca77c2b2ae63;
+const XX_PRIME_5: u64 = 0x27d4eb2f165667c5;
+
+/// a seed that differs from one call to the next
+fn random_seed() -> u64 {
+    RandomState::new().hash_one(0u64)
+}
+
+/// expand a seed into a stream of well mixed values
+fn splitmix64(state: &mut u64) -> u64 {
+    *state = state.wrapping_add(0x9e3779b97f4a7c15);
+    let mut z = *state;
+    z = (z ^ (z >> 30)).wrapping_mul(0xbf58476d1ce4e5b9);
+    z = (z ^ (z >> 27)).wrapping_mul(0x94d049bb133111eb);
+    z ^ (z >> 31)
+}
+
+/// gathers the bytes written to a hasher into little-endian 64-bit
+/// words, holding on to a partial word until it is completed
+#[derive(Clone, Copy, Debug, Default)]
+struct WordBuffer {
+    tail: u64,
+    ntail: usize,
+    length: usize,
+}
+
+impl WordBuffer {
+    /// add bytes, calling f with every word they complete
+    fn write(&mut self, mut bytes: &[u8], mut f: impl FnMut(u64)) {
+        self.length += bytes.len();
+        while self.ntail != 0 && !bytes.is_empty() {
+            self.push(bytes[0]);
+            bytes = &bytes[1..];
+            if self.ntail == 8 {
+                f(self.tail);
+                self.tail = 0;
+                self.ntail = 0;
+            }
+        }
+        let mut words = bytes.chunks_exact(8);
+        for word in &mut words {
+            f(u64::from_le_bytes(word.try_into().unwrap()));
+        }
+        for byte in words.remainder() {
+            self.push(*byte);
+        }
+    }
+
+    fn push(&mut self, byte: u8) {
+        self.tail |= (byte as u64) << (8 * self.ntail);
+        self.ntail += 1;
+    }
+
+    /// the bytes of the partial word
+    fn tail_bytes(&self) -> impl Iterator<Item = u8> + '_ {
+        (0..self.ntail).map(move |i| (self.tail >> (8 * i)) as u8)
+    }
+}
+
+/// FNV-1a, a fast byte-at-a-time hash that is good for short keys but
+/// offers no protection against chosen keys
+#[derive(Clone, Copy, Debug)]
+pub struct FnvHasher {
+    state: u64,
+}
+
+impl Default for FnvHasher {
+    fn default() -> FnvHasher {
+        FnvHasher {
+            state: FNV_OFFSET_BASIS,
+        }
+    }
+}
+
+impl Hasher for FnvHasher {
+    fn write(&mut self, bytes: &[u8]) {
+        for byte in bytes {
+            self.state ^= *byte as u64;
+            self.state = self.state.wrapping_mul(FNV_PRIME);
+        }
+    }
+
+    fn finish(&self) -> u64 {
+        self.state
+    }
+}
+
+#[derive(Clone, Copy, Debug, Default)]
+pub struct FnvBuildHasher;
+
+impl BuildHasher for FnvBuildHasher {
+    type Hasher = FnvHasher;
+
+    fn build_hasher(&self) -> FnvHasher {
+        FnvHasher::default()
+    }
+}
+
+/// SipHash-2-4 keyed with a 128-bit key. With a secret random key this
+/// resists keys chosen to collide
+#[derive(Clone, Copy, Debug)]
+pub struct SipHasher24 {
+    v0: u64,
+    v1: u64,
+    v2: u64,
+    v3: u64,
+    words: WordBuffer,
+}
+
+impl SipHasher24 {
+    pub fn new_with_keys(k0: u64, k1: u64) -> SipHasher24 {
+        SipHasher24 {
+            v0: k0 ^ 0x736f6d6570736575,
+            v1: k1 ^ 0x646f72616e646f6d,
+            v2: k0 ^ 0x6c7967656e657261,
+            v3: k1 ^ 0x7465646279746573,
+            words: WordBuffer::default(),
+        }
+    }
+
+    fn round(&mut self) {
+        self.v0 = self.v0.wrapping_add(self.v1);
+        self.v1 = self.v1.rotate_left(13);
+        self.v1 ^= self.v0;
+        self.v0 = self.v0.rotate_left(32);
+        self.v2 = self.v2.wrapping_add(self.v3);
+        self.v3 = self.v3.rotate_left(16);
+        self.v3 ^= self.v2;
+        self.v0 = self.v0.wrapping_add(self.v3);
+        self.v3 = self.v3.rotate_left(21);
+        self.v3 ^= self.v0;
+        self.v2 = self.v2.wrapping_add(self.v1);
+        self.v1 = self.v1.rotate_left(17);
+        self.v1 ^= self.v2;
+        self.v2 = self.v2.rotate_left(32);
+    }
+
+    fn compress(&mut self, word: u64) {
+        self.v3 ^= word;
+        self.round();
+        self.round();
+        self.v0 ^= word;
+    }
+}
+
+impl Hasher for SipHasher24 {
+    fn write(&mut self, bytes: &[u8]) {
+        let mut words = self.words;
+        words.write(bytes, |word| self.compress(word));
+        self.words = words;
+    }
+
+    fn finish(&self) -> u64 {
+        let mut state = *self;
+        let last = ((self.words.length as u64) << 56) | self.words.tail;
+        state.compress(last);
+        state.v2 ^= 0xff;
+        for _i in 0..4 {
+            state.round();
+        }
+        state.v0 ^ state.v1 ^ state.v2 ^ state.v3
+    }
+}
+
+#[derive(Clone, Copy, Debug)]
+pub struct SipBuildHasher {
+    k0: u64,
+    k1: u64,
+}
+
+impl SipBuildHasher {
+    /// a random key
+    pub fn new() -> SipBuildHasher {
+        let mut state = random_seed();
+        SipBuildHasher::with_keys(splitmix64(&mut state), splitmix64(&mut state))
+    }
+
+    pub fn with_keys(k0: u64, k1: u64) -> SipBuildHasher {
+        SipBuildHasher { k0, k1 }
+    }
+}
+
+impl Default for SipBuildHasher {
+    fn default() -> SipBuildHasher {
+        SipBuildHasher::new()
+    }
+}
+
+impl BuildHasher for SipBuildHasher {
+    type Hasher = SipHasher24;
+
+    fn build_hasher(&self) -> SipHasher24 {
+        SipHasher24::new_with_keys(self.k0, self.k1)
+    }
+}
+
+/// a seeded hash in the style of xxHash: input is consumed in 8-byte
+/// lanes with the XXH64 round and finished with its avalanche. It is
+/// fast for long keys, but its output does not match XXH64
+#[derive(Clone, Copy, Debug)]
+pub struct XxHasher {
+    state: u64,
+    words: WordBuffer,
+}
+
+impl XxHasher {
+    pub fn with_seed(seed: u64) -> XxHasher {
+        XxHasher {
+            state: seed.wrapping_add(XX_PRIME_5),
+            words: WordBuffer::default(),
+        }
+    }
+
+    fn round(acc: u64, input: u64) -> u64 {
+        acc.wrapping_add(input.wrapping_mul(XX_PRIME_2))
+            .rotate_left(31)
+            .wrapping_mul(XX_PRIME_1)
+    }
+
+    fn lane(&mut self, word: u64) {
+        self.state ^= XxHasher::round(0, word);
+        self.state = self
+            .state
+            .rotate_left(27)
+            .wrapping_mul(XX_PRIME_1)
+            .wrapping_add(XX_PRIME_4);
+    }
+}
+
+impl Hasher for XxHasher {
+    fn write(&mut self, bytes: &[u8]) {
+        let mut words = self.words;
+        words.write(bytes, |word| self.lane(word));
+        self.words = words;
+    }
+
+    fn finish(&self) -> u64 {
+        let mut h = self.state.wrapping_add(self.words.length as u64);
+        for byte in self.words.tail_bytes() {
+            h ^= (byte as u64).wrapping_mul(XX_PRIME_5);
+            h = h.rotate_left(11).wrapping_mul(XX_PRIME_1);
+        }
+        h ^= h >> 33;
+        h = h.wrapping_mul(XX_PRIME_2);
+        h ^= h >> 29;
+        h = h.wrapping_mul(XX_PRIME_3);
+        h ^ (h >> 32)
+    }
+}
+
+#[derive(Clone, Copy, Debug)]
+pub struct XxBuildHasher {
+    seed: u64,
+}
+
+impl XxBuildHasher {
+    /// a random seed
+    pub fn new() -> XxBuildHasher {
+        XxBuildHasher::with_seed(random_seed())
+    }
+
+    pub fn with_seed(seed: u64) -> XxBuildHasher {
+        XxBuildHasher { seed }
+    }
+}
+
+impl Default for XxBuildHasher {
+    fn default() -> XxBuildHasher {
+        XxBuildHasher::new()
+    }
+}
+
+impl BuildHasher for XxBuildHasher {
+    type Hasher = XxHasher;
+
+    fn build_hasher(&self) -> XxHasher {
+        XxHasher::with_seed(self.seed)
+    }
+}
+
+/// fold the words written to a hasher through a hash of 64-bit words.
+/// A key of one word hashes to exactly f(word)
+#[derive(Clone, Copy, Debug, Default)]
+struct WordFold {
+    state: u64,
+    words: WordBuffer,
+}
+
+impl WordFold {
+    fn write(&mut self, bytes: &[u8], f: impl Fn(u64) -> u64) {
+        let mut state = self.state;
+        self.words.write(bytes, |word| state = f(state ^ word));
+        self.state = state;
+    }
+
+    fn finish(&self, f: impl Fn(u64) -> u64) -> u64 {
+        if self.words.ntail == 0 {
+            return self.state;
+        }
+        // the length tells apart tails that differ only in zero bytes
+        let last = self.words.tail | ((self.words.length as u64) << 56);
+        f(self.state ^ last)
+    }
+}
+
+/// multiply-add-shift hashing, h(x) = (a * x + b) >> 64 with a and b
+/// 128-bit values and the arithmetic mod 2^128. For a and b drawn at
+/// random this is a strongly universal family on 64-bit keys
+#[derive(Clone, Copy, Debug)]
+pub struct MultiplyShift {
+    a: u128,
+    b: u128,
+}
+
+impl MultiplyShift {
+    /// a random member of the family
+    pub fn new() -> MultiplyShift {
+        MultiplyShift::with_seed(random_seed())
+    }
+
+    /// the member of the family picked by seed
+    pub fn with_seed(seed: u64) -> MultiplyShift {
+        let mut state = seed;
+        let a = ((splitmix64(&mut state) as u128) << 64) | splitmix64(&mut state) as u128;
+        let b = ((splitmix64(&mut state) as u128) << 64) | splitmix64(&mut state) as u128;
+        MultiplyShift { a, b }
+    }
+
+    pub fn hash_u64(&self, x: u64) -> u64 {
+        (self.a.wrapping_mul(x as u128).wrapping_add(self.b) >> 64) as u64
+    }
+}
+
+impl Default for MultiplyShift {
+    fn default() -> MultiplyShift {
+        MultiplyShift::new()
+    }
+}
+
+#[derive(Clone, Copy, Debug)]
+pub struct MultiplyShiftHasher {
+    hash: MultiplyShift,
+    fold: WordFold,
+}
+
+impl Hasher for MultiplyShiftHasher {
+    fn write(&mut self, bytes: &[u8]) {
+        let hash = self.hash;
+        self.fold.write(bytes, |x| hash.hash_u64(x));
+    }
+
+    fn finish(&self) -> u64 {
+        self.fold.finish(|x| self.hash.hash_u64(x))
+    }
+}
+
+impl BuildHasher for MultiplyShift {
+    type Hasher = MultiplyShiftHasher;
+
+    fn build_hasher(&self) -> MultiplyShiftHasher {
+        MultiplyShiftHasher {
+            hash: *self,
+            fold: WordFold::default(),
+        }
+    }
+}
+
+/// simple tabulation hashing: each byte of a 64-bit word indexes its
+/// own table of random values, and the 8 values are xored together.
+/// Only 3-independent, but it behaves close to a truly random function
+/// in linear probing and cuckoo tables. The 16KiB of tables are shared
+/// by reference while hashing, so this is a HashFn but not a BuildHasher
+#[derive(Clone)]
+pub struct Tabulation {
+    tables: Box<[[u64; 256]; 8]>,
+}
+
+impl Tabulation {
+    /// a random member of the family
+    pub fn new() -> Tabulation {
+        Tabulation::with_seed(random_seed())
+    }
+
+    /// the member of the family picked by seed
+    pub fn with_seed(seed: u64) -> Tabulation {
+        let mut state = seed;
+        let mut tables = Box::new([[0u64; 256]; 8]);
+        for table in tables.iter_mut() {
+            for value in table.iter_mut() {
+                *value = splitmix64(&mut state);
+            }
+        }
+        Tabulation { tables }
+    }
+
+    pub fn hash_u64(&self, x: u64) -> u64 {
+        let mut h = 0;
+        for (i, byte) in x.to_le_bytes().iter().enumerate() {
+            h ^= self.tables[i][*byte as usize];
+        }
+        h
+    }
+}
+
+impl Default for Tabulation {
+    fn default() -> Tabulation {
+        Tabulation::new()
+    }
+}
+
+struct TabulationHasher<'a> {
+    tabulation: &'a Tabulation,
+    fold: WordFold,
+}
+
+impl Hasher for TabulationHasher<'_> {
+    fn write(&mut self, bytes: &[u8]) {
+        let tabulation = self.tabulation;
+        self.fold.write(bytes, |x| tabulation.hash_u64(x));
+    }
+
+    fn finish(&self) -> u64 {
+        self.fold.finish(|x| self.tabulation.hash_u64(x))
+    }
+}
+
+impl<K: Hash + ?Sized> HashFn<K> for Tabulation {
+    fn hash(&self, key: &K) -> u64 {
+        let mut hasher = TabulationHasher {
+            tabulation: self,
+            fold: WordFold::default(),
+        };
+        key.hash(&mut hasher);
+        hasher.finish()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn hash_bytes<S: BuildHasher>(hash_builder: &S, bytes: &[u8]) -> u64 {
+        let mut hasher = hash_builder.build_hasher();
+        hasher.write(bytes);
+        hasher.finish()
+    }
+
+    // hash bytes written in two pieces split at every point
+    fn hash_is_independent_of_writes<S: BuildHasher>(hash_builder: &S) {
+        let bytes: Vec<u8> = (0..40).collect();
+        let whole = hash_bytes(hash_builder, &bytes);
+        for split in 0..bytes.len() {
+            let mut hasher = hash_builder.build_hasher();
+            hasher.write(&bytes[..split]);
+            hasher.write(&bytes[split..]);
+            assert_eq!(hasher.finish(), whole);
+        }
+    }
+
+    // hash 4096 sequential keys into 64 buckets, no bucket should be far
+    // above the 64 expected
+    fn spreads_sequential_keys<S: HashFn<u64>>(hash_fn: &S) {
+        let mut buckets = [0; 64];
+        for key in 0..4096u64 {
+            buckets[(hash_fn.hash(&key) % 64) as usize] += 1;
+        }
+        assert!(buckets.iter().all(|count| *count > 32 && *count < 100));
+    }
+
+    #[test]
+    fn fnv_matches_reference() {
+        assert_eq!(hash_bytes(&FnvBuildHasher, b""), 0xcbf29ce484222325);
+        assert_eq!(hash_bytes(&FnvBuildHasher, b"a"), 0xaf63dc4c8601ec8c);
+        assert_eq!(hash_bytes(&FnvBuildHasher, b"foobar"), 0x85944171f73967e8);
+        hash_is_independent_of_writes(&FnvBuildHasher);
+        spreads_sequential_keys(&FnvBuildHasher);
+    }
+
+    #[test]
+    #[allow(deprecated)]
+    fn siphash_matches_reference() {
+        // std's deprecated SipHasher is SipHash-2-4
+        let sip = SipBuildHasher::with_keys(0x0706050403020100, 0x0f0e0d0c0b0a0908);
+        assert_eq!(hash_bytes(&sip, b""), 0x726fdb47dd0e0e31);
+        let bytes: Vec<u8> = (0..64).collect();
+        for len in 0..bytes.len() {
+            let mut reference =
+                std::hash::SipHasher::new_with_keys(0x0706050403020100, 0x0f0e0d0c0b0a0908);
+            reference.write(&bytes[..len]);
+            assert_eq!(hash_bytes(&sip, &bytes[..len]), reference.finish());
+        }
+        hash_is_independent_of_writes(&sip);
+        spreads_sequential_keys(&SipBuildHasher::new());
+    }
+
+    #[test]
+    fn xx_hash_is_seeded() {
+        let a = XxBuildHasher::with_seed(1);
+        let b = XxBuildHasher::with_seed(2);
+        assert_eq!(hash_bytes(&a, b"key"), hash_bytes(&a, b"key"));
+        assert_ne!(hash_bytes(&a, b"key"), hash_bytes(&b, b"key"));
+        assert_ne!(hash_bytes(&a, b"key"), hash_bytes(&a, b"key\0"));
+        hash_is_independent_of_writes(&a);
+        spreads_sequential_keys(&a);
+    }
+
+    #[test]
+    fn multiply_shift_hashes_words_directly() {
+        let a = MultiplyShift::with_seed(1);
+        let b = MultiplyShift::with_seed(2);
+        for key in [0u64, 1, 42, u64::MAX] {
+            assert_eq!(a.hash_one(key), a.hash_u64(key));
+        }
+        assert_eq!(MultiplyShift::with_seed(1).hash_u64(42), a.hash_u64(42));
+        assert_ne!(a.hash_u64(42), b.hash_u64(42));
+        assert_ne!(hash_bytes(&a, b"\0"), hash_bytes(&a, b"\0\0"));
+        hash_is_independent_of_writes(&a);
+        spreads_sequential_keys(&a);
+    }
+
+    #[test]
+    fn tabulation_hashes_words_directly() {
+        let a = Tabulation::with_seed(1);
+        let b = Tabulation::with_seed(2);
+        for key in [0u64, 1, 42, u64::MAX] {
+            assert_eq!(HashFn::hash(&a, &key), a.hash_u64(key));
+        }
+        assert_eq!(Tabulation::with_seed(1).hash_u64(42), a.hash_u64(42));
+        assert_ne!(a.hash_u64(42), b.hash_u64(42));
+        assert_ne!(HashFn::hash(&a, "key"), HashFn::hash(&a, "key2"));
+        spreads_sequential_keys(&a);
+    }
+}
@@ -0,0 +1,67 @@
+// Iterator adaptors every table shares, built on the iterators of the
+// slots and chain modules or on ones of a table's own.
+
+use std::marker::PhantomData;
+
+/// iterator over the keys of a table, from an iterator over its entries
+pub struct Keys<I> {
+    inner: I,
+}
+
+/// iterator over the data of a table, from an iterator over its entries
+pub struct Values<I> {
+    inner: I,
+}
+
+/// iterator over the entries removed from a table by drain, owning the
+/// storage the table gave up. The table is empty as soon as drain
+/// returns, whether or not this is run to completion
+pub struct Drain<'a, I> {
+    inner: I,
+    _phantom_table: PhantomData<&'a mut ()>,
+}
+
+impl<I> Keys<I> {
+    pub fn new(inner: I) -> Keys<I> {
+        Keys { inner }
+    }
+}
+
+impl<I> Values<I> {
+    pub fn new(inner: I) -> Values<I> {
+        Values { inner }
+    }
+}
+
+impl<I> Drain<'_, I> {
+    pub fn new(inner: I) -> Self {
+        Drain {
+            inner,
+            _phantom_table: PhantomData {},
+        }
+    }
+}
+
+impl<'a, K: 'a, U: 'a, I: Iterator<Item = (&'a K, &'a U)>> Iterator for Keys<I> {
+    type Item = &'a K;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.inner.next().map(|(key, _)| key)
+    }
+}
+
+impl<'a, K: 'a, U: 'a, I: Iterator<Item = (&'a K, &'a U)>> Iterator for Values<I> {
+    type Item = &'a U;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.inner.next().map(|(_, data)| data)
+    }
+}
+
+impl<I: Iterator> Iterator for Drain<'_, I> {
+    type Item = I::Item;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.inner.next()
+    }
+}
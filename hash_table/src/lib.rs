@@ -1,31 +1,85 @@
+use std::hash::{BuildHasher, Hash};
+
+pub mod chain;
+pub mod entry;
+pub mod hashers;
+pub mod iter;
+pub mod slots;
+#[cfg(any(test, feature = "testing"))]
+pub mod testing;
+
 #[derive(Debug)]
 pub enum HashTableError {
     NotFound,
     TableFull,
+    KeyExists,
+    RehashFailed,
 }
 
 #[derive(Debug, Clone, PartialEq)]
-pub struct HashTableEntry<U> {
-    pub key: u16,
+pub struct HashTableEntry<K, U> {
+    pub key: K,
     pub data: Option<Box<U>>,
 }
 
-impl<U: Default> Default for HashTableEntry<U> {
-    fn default() -> HashTableEntry<U> {
-        HashTableEntry::<U> {
-            key: u16::default(),
+impl<K: Default, U> Default for HashTableEntry<K, U> {
+    fn default() -> HashTableEntry<K, U> {
+        HashTableEntry::<K, U> {
+            key: K::default(),
             data: None,
         }
     }
 }
 
-pub trait HashTable<U> {
-    fn insert(&mut self, key: u16, data: U) -> Result<(), HashTableError>;
-    fn delete(&mut self, key: u16) -> Result<(), HashTableError>;
-    fn lookup(&self, key: u16) -> Result<U, HashTableError>;
+pub trait HashTable<K: Hash + Eq, U> {
+    /// insert data under key, replacing the data of an existing entry.
+    /// Returns the replaced data, or None if key was not present
+    fn insert(&mut self, key: K, data: U) -> Result<Option<U>, HashTableError>;
+    /// insert data under key, failing with KeyExists rather than
+    /// replacing the data of an existing entry
+    fn try_insert(&mut self, key: K, data: U) -> Result<(), HashTableError> {
+        if self.get(&key).is_some() {
+            return Err(HashTableError::KeyExists);
+        }
+        self.insert(key, data).map(|_| ())
+    }
+    /// remove the entry for key, returning its data
+    fn remove(&mut self, key: &K) -> Result<U, HashTableError>;
+    /// remove the entry for key, discarding its data
+    fn delete(&mut self, key: &K) -> Result<(), HashTableError> {
+        self.remove(key).map(|_| ())
+    }
+    /// a reference to the data stored under key
+    fn get(&self, key: &K) -> Option<&U>;
+    /// a mutable reference to the data stored under key
+    fn get_mut(&mut self, key: &K) -> Option<&mut U>;
+    /// a copy of the data stored under key
+    fn lookup(&self, key: &K) -> Result<U, HashTableError>
+    where
+        U: Clone,
+    {
+        self.get(key).cloned().ok_or(HashTableError::NotFound)
+    }
+    /// number of entries in the table
+    fn len(&self) -> usize;
+    fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+    /// remove every entry, keeping the capacity of the table
+    fn clear(&mut self);
+    /// number of slots, or buckets for chained tables, the table has
+    fn capacity(&self) -> usize;
 }
 
-pub trait HashFn {
-    fn hash(&self, key: u16) -> u16;
+/// hashes a key to 64 bits, which a table reduces to a slot index.
+/// Every BuildHasher is a HashFn, so std's RandomState and the hashers
+/// in the hashers module can be handed to any table builder
+pub trait HashFn<K: ?Sized> {
+    fn hash(&self, key: &K) -> u64;
 }
 
+impl<K: Hash + ?Sized, S: BuildHasher> HashFn<K> for S {
+    fn hash(&self, key: &K) -> u64 {
+        self.hash_one(key)
+    }
+}
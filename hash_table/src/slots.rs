@@ -0,0 +1,198 @@
+// Iterators and entries for tables that keep their entries in slots of
+// HashTableEntry, as the open addressing tables do. A slot is full when
+// it holds data, and the iterators skip the rest. They walk a slice of
+// slots unless given some other iterator over them. Entries read and
+// write the slots through the SlotTable trait, leaving where a key goes
+// and what else has to move to the table.
+
+use crate::entry::{Occupied, Vacant};
+use crate::HashTableEntry;
+use std::marker::PhantomData;
+
+/// a table keeping its entries in a slice of slots, for the entries of
+/// this module
+pub trait SlotTable {
+    type Key;
+    type Data;
+    /// what a vacant entry found out about where its key goes, the slot
+    /// and anything else the table needs to insert it there
+    type Vacancy;
+
+    fn slots(&self) -> &[HashTableEntry<Self::Key, Self::Data>];
+
+    fn slots_mut(&mut self) -> &mut [HashTableEntry<Self::Key, Self::Data>];
+
+    /// insert key and data where vacancy says, returning the slot that
+    /// holds them afterwards
+    fn place(&mut self, vacancy: Self::Vacancy, key: Self::Key, data: Box<Self::Data>) -> usize;
+
+    /// empty the full slot and return its data
+    fn take(&mut self, slot: usize) -> Box<Self::Data>;
+}
+
+/// iterator over the key and data of every full slot, in slot order
+pub struct Iter<'a, K, U, I = std::slice::Iter<'a, HashTableEntry<K, U>>> {
+    slots: I,
+    _phantom_slots: PhantomData<&'a HashTableEntry<K, U>>,
+}
+
+/// iterator over the key and mutable data of every full slot
+pub struct IterMut<'a, K, U, I = std::slice::IterMut<'a, HashTableEntry<K, U>>> {
+    slots: I,
+    _phantom_slots: PhantomData<&'a mut HashTableEntry<K, U>>,
+}
+
+/// owning iterator over the entries of every full slot
+pub struct IntoIter<K, U, I = std::vec::IntoIter<HashTableEntry<K, U>>> {
+    slots: I,
+    _phantom_slots: PhantomData<HashTableEntry<K, U>>,
+}
+
+impl<'a, K, U, I: Iterator<Item = &'a HashTableEntry<K, U>>> Iter<'a, K, U, I> {
+    pub fn new<T: IntoIterator<IntoIter = I>>(slots: T) -> Iter<'a, K, U, I> {
+        Iter {
+            slots: slots.into_iter(),
+            _phantom_slots: PhantomData {},
+        }
+    }
+}
+
+impl<'a, K, U, I: Iterator<Item = &'a mut HashTableEntry<K, U>>> IterMut<'a, K, U, I> {
+    pub fn new<T: IntoIterator<IntoIter = I>>(slots: T) -> IterMut<'a, K, U, I> {
+        IterMut {
+            slots: slots.into_iter(),
+            _phantom_slots: PhantomData {},
+        }
+    }
+}
+
+impl<K, U, I: Iterator<Item = HashTableEntry<K, U>>> IntoIter<K, U, I> {
+    pub fn new<T: IntoIterator<IntoIter = I>>(slots: T) -> IntoIter<K, U, I> {
+        IntoIter {
+            slots: slots.into_iter(),
+            _phantom_slots: PhantomData {},
+        }
+    }
+}
+
+impl<'a, K: 'a, U: 'a, I: Iterator<Item = &'a HashTableEntry<K, U>>> Iterator
+    for Iter<'a, K, U, I>
+{
+    type Item = (&'a K, &'a U);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        for slot in self.slots.by_ref() {
+            if let Some(data) = slot.data.as_deref() {
+                return Some((&slot.key, data));
+            }
+        }
+        None
+    }
+}
+
+impl<'a, K: 'a, U: 'a, I: Iterator<Item = &'a mut HashTableEntry<K, U>>> Iterator
+    for IterMut<'a, K, U, I>
+{
+    type Item = (&'a K, &'a mut U);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        for slot in self.slots.by_ref() {
+            if let Some(data) = slot.data.as_deref_mut() {
+                return Some((&slot.key, data));
+            }
+        }
+        None
+    }
+}
+
+impl<K, U, I: Iterator<Item = HashTableEntry<K, U>>> Iterator for IntoIter<K, U, I> {
+    type Item = (K, U);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        for slot in self.slots.by_ref() {
+            if let Some(data) = slot.data {
+                return Some((slot.key, *data));
+            }
+        }
+        None
+    }
+}
+
+/// the full slot holding the requested key
+pub struct OccupiedEntry<'a, T> {
+    table: &'a mut T,
+    slot: usize,
+}
+
+/// where the requested key will be inserted, found while probing for
+/// the key
+pub struct VacantEntry<'a, T: SlotTable> {
+    table: &'a mut T,
+    key: T::Key,
+    vacancy: T::Vacancy,
+}
+
+impl<'a, T: SlotTable> OccupiedEntry<'a, T> {
+    pub fn new(table: &'a mut T, slot: usize) -> OccupiedEntry<'a, T> {
+        OccupiedEntry { table, slot }
+    }
+}
+
+impl<'a, T: SlotTable> VacantEntry<'a, T> {
+    pub fn new(table: &'a mut T, key: T::Key, vacancy: T::Vacancy) -> VacantEntry<'a, T> {
+        VacantEntry {
+            table,
+            key,
+            vacancy,
+        }
+    }
+}
+
+impl<'a, T: SlotTable> Occupied<'a> for OccupiedEntry<'a, T> {
+    type Key = T::Key;
+    type Data = T::Data;
+
+    fn key(&self) -> &T::Key {
+        &self.table.slots()[self.slot].key
+    }
+
+    fn get(&self) -> &T::Data {
+        self.table.slots()[self.slot].data.as_deref().unwrap()
+    }
+
+    fn get_mut(&mut self) -> &mut T::Data {
+        self.table.slots_mut()[self.slot]
+            .data
+            .as_deref_mut()
+            .unwrap()
+    }
+
+    fn into_mut(self) -> &'a mut T::Data {
+        self.table.slots_mut()[self.slot]
+            .data
+            .as_deref_mut()
+            .unwrap()
+    }
+
+    fn remove(self) -> T::Data {
+        *self.table.take(self.slot)
+    }
+}
+
+impl<'a, T: SlotTable> Vacant<'a> for VacantEntry<'a, T> {
+    type Key = T::Key;
+    type Data = T::Data;
+
+    fn key(&self) -> &T::Key {
+        &self.key
+    }
+
+    fn into_key(self) -> T::Key {
+        self.key
+    }
+
+    fn insert(self, data: T::Data) -> &'a mut T::Data {
+        let slot = self.table.place(self.vacancy, self.key, Box::new(data));
+        self.table.slots_mut()[slot].data.as_deref_mut().unwrap()
+    }
+}
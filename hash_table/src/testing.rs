@@ -0,0 +1,225 @@
+// Tests every table has to pass, whatever its algorithm. A table's
+// crate implements TableBuilder for it and calls check_table from one
+// of its tests, keeping only the tests of its own algorithm alongside.
+
+use crate::entry::{Entry, EntryTable, Occupied, Vacant};
+use crate::hashers::{FnvBuildHasher, MultiplyShift, SipBuildHasher, Tabulation, XxBuildHasher};
+use crate::{HashFn, HashTable, HashTableError};
+use std::hash::Hash;
+
+/// builds the tables check_table runs against
+pub trait TableBuilder {
+    type Table<K: Hash + Eq + Default, U, S: HashFn<K>>: HashTable<K, U>
+        + EntryTable<K, U>
+        + IntoIterator<Item = (K, U)>;
+
+    /// an empty table hashing keys with hash_builder, which grows to
+    /// hold as many entries as are inserted
+    fn build<K: Hash + Eq + Default, U, S: HashFn<K>>(
+        &self,
+        hash_builder: S,
+    ) -> Self::Table<K, U, S>;
+}
+
+/// run every shared test against the tables builder builds
+pub fn check_table<B: TableBuilder>(builder: B) {
+    can_use_string_keys(&builder);
+    insert_replaces_existing_data(&builder);
+    try_insert_fails_on_existing_key(&builder);
+    remove_returns_data(&builder);
+    can_get_and_modify_non_copy_data(&builder);
+    can_use_entry(&builder);
+    len_tracks_entries(&builder);
+    iterates_every_entry(&builder);
+    can_use_provided_hashers(&builder);
+}
+
+fn can_use_string_keys<B: TableBuilder>(builder: &B) {
+    let mut x = builder.build::<String, u16, _>(FnvBuildHasher);
+    assert!(x.insert("one".to_string(), 10).is_ok());
+    assert!(x.insert("two".to_string(), 20).is_ok());
+
+    let ret = x.lookup(&"one".to_string());
+    assert!(ret.is_ok());
+    assert_eq!(ret.unwrap(), 10);
+    let ret = x.lookup(&"two".to_string());
+    assert!(ret.is_ok());
+    assert_eq!(ret.unwrap(), 20);
+    let ret = x.lookup(&"three".to_string());
+    assert!(ret.is_err());
+}
+
+fn insert_replaces_existing_data<B: TableBuilder>(builder: &B) {
+    let mut x = builder.build::<u16, u16, _>(FnvBuildHasher);
+    let ret = x.insert(1, 10);
+    assert!(ret.is_ok());
+    assert_eq!(ret.unwrap(), None);
+    let ret = x.insert(2, 20);
+    assert!(ret.is_ok());
+    assert_eq!(ret.unwrap(), None);
+    let ret = x.insert(1, 100);
+    assert!(ret.is_ok());
+    assert_eq!(ret.unwrap(), Some(10));
+
+    let ret = x.lookup(&1);
+    assert!(ret.is_ok());
+    assert_eq!(ret.unwrap(), 100);
+
+    // only one entry is held for the key
+    assert!(x.delete(&1).is_ok());
+    assert!(x.lookup(&1).is_err());
+    let ret = x.lookup(&2);
+    assert!(ret.is_ok());
+    assert_eq!(ret.unwrap(), 20);
+}
+
+fn try_insert_fails_on_existing_key<B: TableBuilder>(builder: &B) {
+    let mut x = builder.build::<u16, u16, _>(FnvBuildHasher);
+    assert!(x.try_insert(1, 10).is_ok());
+    let ret = x.try_insert(1, 100);
+    assert!(matches!(ret, Err(HashTableError::KeyExists)));
+    let ret = x.lookup(&1);
+    assert!(ret.is_ok());
+    assert_eq!(ret.unwrap(), 10);
+}
+
+fn remove_returns_data<B: TableBuilder>(builder: &B) {
+    let mut x = builder.build::<u16, u16, _>(FnvBuildHasher);
+    assert!(x.insert(1, 10).is_ok());
+    assert!(x.insert(2, 20).is_ok());
+
+    let ret = x.remove(&1);
+    assert!(ret.is_ok());
+    assert_eq!(ret.unwrap(), 10);
+    assert!(x.lookup(&1).is_err());
+
+    let ret = x.remove(&1);
+    assert!(matches!(ret, Err(HashTableError::NotFound)));
+    let ret = x.delete(&3);
+    assert!(matches!(ret, Err(HashTableError::NotFound)));
+
+    let ret = x.remove(&2);
+    assert!(ret.is_ok());
+    assert_eq!(ret.unwrap(), 20);
+}
+
+fn can_get_and_modify_non_copy_data<B: TableBuilder>(builder: &B) {
+    let mut x = builder.build::<u16, String, _>(FnvBuildHasher);
+    assert!(x.insert(1, "one".to_string()).is_ok());
+    assert!(x.insert(2, "two".to_string()).is_ok());
+
+    assert_eq!(x.get(&1), Some(&"one".to_string()));
+    assert_eq!(x.get(&3), None);
+
+    x.get_mut(&2).unwrap().push_str("-2");
+    assert_eq!(x.get(&2).map(String::as_str), Some("two-2"));
+    assert!(x.get_mut(&3).is_none());
+
+    let ret = x.lookup(&2);
+    assert!(ret.is_ok());
+    assert_eq!(ret.unwrap(), "two-2");
+    let ret = x.remove(&1);
+    assert!(ret.is_ok());
+    assert_eq!(ret.unwrap(), "one");
+    assert_eq!(x.get(&1), None);
+}
+
+fn can_use_entry<B: TableBuilder>(builder: &B) {
+    let mut x = builder.build::<u16, u16, _>(FnvBuildHasher);
+    assert_eq!(*x.entry(1).unwrap().or_insert(10), 10);
+    assert_eq!(*x.entry(1).unwrap().or_insert(20), 10);
+    *x.entry(2).unwrap().or_insert_with(|| 20) += 1;
+    assert_eq!(x.get(&2), Some(&21));
+
+    x.entry(1)
+        .unwrap()
+        .and_modify(|data| *data += 5)
+        .or_insert(0);
+    x.entry(3)
+        .unwrap()
+        .and_modify(|data| *data += 5)
+        .or_insert(30);
+    assert_eq!(x.get(&1), Some(&15));
+    assert_eq!(x.get(&3), Some(&30));
+    assert_eq!(*x.entry(5).unwrap().or_default(), 0);
+
+    match x.entry(2).unwrap() {
+        Entry::Occupied(mut entry) => {
+            assert_eq!(*entry.key(), 2);
+            assert_eq!(*entry.get(), 21);
+            assert_eq!(entry.insert(22), 21);
+            assert_eq!(entry.remove(), 22);
+        }
+        Entry::Vacant(_) => panic!("key 2 should be occupied"),
+    }
+    assert!(x.get(&2).is_none());
+    match x.entry(4).unwrap() {
+        Entry::Occupied(_) => panic!("key 4 should be vacant"),
+        Entry::Vacant(entry) => {
+            assert_eq!(*entry.key(), 4);
+            *entry.insert(40) += 4;
+        }
+    }
+    assert_eq!(x.get(&4), Some(&44));
+    assert_eq!(x.len(), 4);
+}
+
+fn len_tracks_entries<B: TableBuilder>(builder: &B) {
+    let mut x = builder.build::<u16, u16, _>(FnvBuildHasher);
+    assert!(x.is_empty());
+    assert!(x.insert(1, 10).is_ok());
+    assert!(x.insert(2, 20).is_ok());
+    assert!(x.insert(2, 21).is_ok());
+    assert_eq!(x.len(), 2);
+    assert!(x.delete(&1).is_ok());
+    assert!(x.delete(&1).is_err());
+    assert_eq!(x.len(), 1);
+    x.entry(3).unwrap().or_insert(30);
+    assert_eq!(x.len(), 2);
+
+    let capacity = x.capacity();
+    x.clear();
+    assert!(x.is_empty());
+    assert_eq!(x.capacity(), capacity);
+    assert!(x.get(&2).is_none());
+    assert!(x.insert(2, 20).is_ok());
+    assert_eq!(x.len(), 1);
+}
+
+fn iterates_every_entry<B: TableBuilder>(builder: &B) {
+    let mut x = builder.build::<u16, u16, _>(FnvBuildHasher);
+    for key in 0..100 {
+        assert!(x.insert(key, key * 10).is_ok());
+    }
+    for key in (0..100).step_by(3) {
+        assert!(x.delete(&key).is_ok());
+    }
+    let mut entries: Vec<(u16, u16)> = x.into_iter().collect();
+    entries.sort();
+    let expected: Vec<(u16, u16)> = (0..100)
+        .filter(|key| key % 3 != 0)
+        .map(|key| (key, key * 10))
+        .collect();
+    assert_eq!(entries, expected);
+}
+
+fn fill_and_check<T: HashTable<u16, u16>>(mut x: T) {
+    for key in 0..100 {
+        assert!(x.insert(key, key * 10).is_ok());
+    }
+    for key in 0..100 {
+        assert_eq!(x.get(&key), Some(&(key * 10)));
+    }
+    assert!(x.delete(&50).is_ok());
+    assert!(x.get(&50).is_none());
+    assert_eq!(x.len(), 99);
+}
+
+fn can_use_provided_hashers<B: TableBuilder>(builder: &B) {
+    fill_and_check(builder.build::<u16, u16, _>(FnvBuildHasher));
+    fill_and_check(builder.build::<u16, u16, _>(MultiplyShift::with_seed(7)));
+    fill_and_check(builder.build::<u16, u16, _>(Tabulation::with_seed(7)));
+    fill_and_check(builder.build::<u16, u16, _>(XxBuildHasher::with_seed(3)));
+    fill_and_check(builder.build::<u16, u16, _>(SipBuildHasher::with_keys(1, 2)));
+    fill_and_check(builder.build::<u16, u16, _>(std::collections::hash_map::RandomState::new()));
+}
@@ -0,0 +1,58 @@
+use crate::HopscotchHash;
+use hash_table::entry::EntryTable;
+use hash_table::slots::{self, SlotTable};
+use hash_table::{HashFn, HashTableEntry, HashTableError};
+use std::collections::hash_map::RandomState;
+use std::hash::Hash;
+
+/// a view into a single slot of a HopscotchHash, which is either occupied
+/// by the key it was requested for or vacant and ready to take it
+pub type Entry<'a, K, U, S = RandomState> =
+    hash_table::entry::Entry<OccupiedEntry<'a, K, U, S>, VacantEntry<'a, K, U, S>>;
+
+/// the slot holding the requested key
+pub type OccupiedEntry<'a, K, U, S = RandomState> =
+    slots::OccupiedEntry<'a, HopscotchHash<K, U, S>>;
+
+/// the empty slot in the neighbourhood of the requested key's home
+/// slot that the key will be inserted into, made by displacing entries
+/// if need be
+pub type VacantEntry<'a, K, U, S = RandomState> = slots::VacantEntry<'a, HopscotchHash<K, U, S>>;
+
+impl<K: Hash + Eq + Default, U, S: HashFn<K>> SlotTable for HopscotchHash<K, U, S> {
+    type Key = K;
+    type Data = U;
+    type Vacancy = usize;
+
+    fn slots(&self) -> &[HashTableEntry<K, U>] {
+        &self.data
+    }
+
+    fn slots_mut(&mut self) -> &mut [HashTableEntry<K, U>] {
+        &mut self.data
+    }
+
+    fn place(&mut self, y: usize, key: K, data: Box<U>) -> usize {
+        HopscotchHash::place(self, y, key, data);
+        y
+    }
+
+    fn take(&mut self, y: usize) -> Box<U> {
+        HopscotchHash::take(self, y)
+    }
+}
+
+impl<K: Hash + Eq + Default, U, S: HashFn<K>> EntryTable<K, U> for HopscotchHash<K, U, S> {
+    type Occupied<'a>
+        = OccupiedEntry<'a, K, U, S>
+    where
+        Self: 'a;
+    type Vacant<'a>
+        = VacantEntry<'a, K, U, S>
+    where
+        Self: 'a;
+
+    fn entry(&mut self, key: K) -> Result<Entry<'_, K, U, S>, HashTableError> {
+        HopscotchHash::entry(self, key)
+    }
+}
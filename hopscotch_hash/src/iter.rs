@@ -0,0 +1,67 @@
+use crate::HopscotchHash;
+use hash_table::{iter, HashFn};
+use std::hash::Hash;
+
+pub use hash_table::slots::{IntoIter, Iter, IterMut};
+
+pub type Keys<'a, K, U> = iter::Keys<Iter<'a, K, U>>;
+
+pub type Values<'a, K, U> = iter::Values<Iter<'a, K, U>>;
+
+/// iterator over the entries removed from a HopscotchHash by drain. The
+/// table is empty as soon as drain returns, whether or not this is
+/// run to completion
+pub type Drain<'a, K, U> = iter::Drain<'a, IntoIter<K, U>>;
+
+impl<K, U, S> HopscotchHash<K, U, S> {
+    pub fn iter(&self) -> Iter<'_, K, U> {
+        Iter::new(&self.data)
+    }
+
+    pub fn iter_mut(&mut self) -> IterMut<'_, K, U> {
+        IterMut::new(&mut self.data)
+    }
+
+    pub fn keys(&self) -> Keys<'_, K, U> {
+        Keys::new(self.iter())
+    }
+
+    pub fn values(&self) -> Values<'_, K, U> {
+        Values::new(self.iter())
+    }
+}
+
+impl<K: Hash + Eq + Default, U, S: HashFn<K>> HopscotchHash<K, U, S> {
+    /// remove every entry, keeping the capacity of the table
+    pub fn drain(&mut self) -> Drain<'_, K, U> {
+        let slots = self.reset(self.get_capacity());
+        Drain::new(IntoIter::new(slots))
+    }
+}
+
+impl<K, U, S> IntoIterator for HopscotchHash<K, U, S> {
+    type Item = (K, U);
+    type IntoIter = IntoIter<K, U>;
+
+    fn into_iter(self) -> IntoIter<K, U> {
+        IntoIter::new(self.data)
+    }
+}
+
+impl<'a, K, U, S> IntoIterator for &'a HopscotchHash<K, U, S> {
+    type Item = (&'a K, &'a U);
+    type IntoIter = Iter<'a, K, U>;
+
+    fn into_iter(self) -> Iter<'a, K, U> {
+        self.iter()
+    }
+}
+
+impl<'a, K, U, S> IntoIterator for &'a mut HopscotchHash<K, U, S> {
+    type Item = (&'a K, &'a mut U);
+    type IntoIter = IterMut<'a, K, U>;
+
+    fn into_iter(self) -> IterMut<'a, K, U> {
+        self.iter_mut()
+    }
+}
@@ -0,0 +1,641 @@
+#![allow(dead_code)]
+
+use hash_table::{HashFn, HashTable, HashTableEntry, HashTableError};
+use std::collections::hash_map::RandomState;
+use std::hash::Hash;
+use std::marker::PhantomData;
+use std::mem;
+
+mod entry;
+mod iter;
+
+pub use entry::{Entry, OccupiedEntry, VacantEntry};
+pub use hash_table::entry::{Occupied, Vacant};
+pub use iter::{Drain, IntoIter, Iter, IterMut, Keys, Values};
+
+const DEFAULT_GROWTH_FACTOR: f32 = 2.0;
+const DEFAULT_NEIGHBOURHOOD: usize = 32;
+const MAX_NEIGHBOURHOOD: usize = 64;
+
+pub struct HopscotchHash<K, U, S = RandomState> {
+    data: Vec<HashTableEntry<K, U>>,
+    // hop bitmap of each slot, bit i is set when slot + i holds an entry
+    // whose home is this slot. Every entry lies within neighbourhood
+    // slots of its home, so a lookup only looks at the slots of one
+    // bitmap
+    hops: Vec<u64>,
+    capacity: usize,
+    // number of slots from its home an entry may be placed, at most 64
+    neighbourhood: usize,
+    // number of slots holding an entry
+    len: usize,
+    // grow once entries would exceed this fraction of capacity, or an
+    // insert finds no room in its neighbourhood. None disables growing
+    max_load_factor: Option<f32>,
+    // shrink once entries fall below this fraction of capacity, None
+    // disables shrinking
+    min_load_factor: Option<f32>,
+    // capacity is multiplied by this when growing, divided when shrinking
+    growth_factor: f32,
+    // hashes keys, the home slot of a key is its hash modulo capacity
+    hash_builder: S,
+}
+
+impl<K, U, S> HopscotchHash<K, U, S> {
+    fn get_capacity(&self) -> usize {
+        self.capacity
+    }
+
+    fn get_neighbourhood(&self) -> usize {
+        self.neighbourhood
+    }
+
+    /// the slot offset slots on from y
+    fn offset(&self, y: usize, offset: usize) -> usize {
+        (y + offset) % self.get_capacity()
+    }
+
+    /// how far slot y is from slot home
+    fn distance(&self, home: usize, y: usize) -> usize {
+        (y + self.get_capacity() - home) % self.get_capacity()
+    }
+}
+
+impl<K: Hash + Eq + Default, U, S: HashFn<K>> HopscotchHash<K, U, S> {
+    /// get the entry for key for in-place manipulation. Room for the key
+    /// is made up front, so this fails with TableFull if the key is
+    /// absent and there is no room in its neighbourhood that growing
+    /// the table could make
+    pub fn entry(&mut self, key: K) -> Result<Entry<'_, K, U, S>, HashTableError> {
+        if let Some(slot) = self.find_slot(&key) {
+            return Ok(Entry::Occupied(OccupiedEntry::new(self, slot)));
+        }
+        self.reserve_one();
+        loop {
+            if let Some(slot) = self.make_room(&key) {
+                return Ok(Entry::Vacant(VacantEntry::new(self, key, slot)));
+            }
+            if self.max_load_factor.is_none() {
+                return Err(HashTableError::TableFull);
+            }
+            self.resize(self.grown_capacity(self.get_capacity()));
+        }
+    }
+}
+
+impl<K: Hash + Eq + Default, U, S: HashFn<K>> HopscotchHash<K, U, S> {
+    /// find the slot holding key, looking only at the slots the hop
+    /// bitmap of its home slot marks
+    fn find_slot(&self, key: &K) -> Option<usize> {
+        if self.get_capacity() == 0 {
+            return None;
+        }
+        let home = self.hash(key);
+        let mut hops = self.hops[home];
+        while hops != 0 {
+            let y = self.offset(home, hops.trailing_zeros() as usize);
+            if self.data[y].key == *key {
+                return Some(y);
+            }
+            hops &= hops - 1;
+        }
+        None
+    }
+
+    /// an empty slot in the neighbourhood of key's home. The nearest
+    /// empty slot is found by linear probing, and while that is outside
+    /// the neighbourhood an entry between the two is hopped into it,
+    /// bringing it closer. None if there is no empty slot or no entry
+    /// can be hopped
+    fn make_room(&mut self, key: &K) -> Option<usize> {
+        if self.len == self.get_capacity() {
+            return None;
+        }
+        let home = self.hash(key);
+        let mut distance =
+            (0..self.get_capacity()).find(|d| self.data[self.offset(home, *d)].data.is_none())?;
+        while distance >= self.get_neighbourhood() {
+            let empty = self.offset(home, distance);
+            let (from, hop) = self.hop_candidate(empty)?;
+            // the slot the entry leaves is the new empty slot
+            distance -= self.distance(from, empty) - hop;
+            self.hop(from, hop, empty);
+        }
+        Some(self.offset(home, distance))
+    }
+
+    /// the entry nearest the front of the neighbourhood before the empty
+    /// slot that can move into it without leaving its own neighbourhood,
+    /// as its home and its offset from there
+    fn hop_candidate(&self, empty: usize) -> Option<(usize, usize)> {
+        let neighbourhood = self.get_neighbourhood();
+        (1..neighbourhood).rev().find_map(|back| {
+            let from = self.offset(empty, self.get_capacity() - back);
+            // entries of from lying before the empty slot
+            let hops = self.hops[from] & ((1 << back) - 1);
+            (hops != 0).then(|| (from, hops.trailing_zeros() as usize))
+        })
+    }
+
+    /// move the entry hop slots on from home into the empty slot
+    fn hop(&mut self, home: usize, hop: usize, empty: usize) {
+        let y = self.offset(home, hop);
+        self.data[empty] = mem::take(&mut self.data[y]);
+        self.hops[home] ^= (1 << hop) | (1 << self.distance(home, empty));
+    }
+
+    /// insert into the empty slot y, in the neighbourhood of key's home
+    fn place(&mut self, y: usize, key: K, data: Box<U>) {
+        let home = self.hash(&key);
+        self.hops[home] |= 1 << self.distance(home, y);
+        self.data[y].key = key;
+        self.data[y].data = Some(data);
+        self.len += 1;
+    }
+
+    /// empty the occupied slot y, clearing its bit in the hop bitmap of
+    /// its home, and return its data
+    fn take(&mut self, y: usize) -> Box<U> {
+        let home = self.hash(&self.data[y].key);
+        self.hops[home] &= !(1 << self.distance(home, y));
+        let data = self.data[y].data.take().unwrap();
+        self.len -= 1;
+        self.shrink_to_fit_load();
+        data
+    }
+
+    /// grow the table if one more entry would exceed the maximum load
+    /// factor
+    fn reserve_one(&mut self) {
+        let max_load_factor = match self.max_load_factor {
+            Some(max_load_factor) => max_load_factor,
+            None => return,
+        };
+        if ((self.len + 1) as f32) <= self.get_capacity() as f32 * max_load_factor {
+            return;
+        }
+        self.resize(self.grown_capacity(self.get_capacity()));
+    }
+
+    /// shrink the table if entries have fallen below the minimum load
+    /// factor, as long as the result stays under the maximum load factor
+    fn shrink_to_fit_load(&mut self) {
+        let min_load_factor = match self.min_load_factor {
+            Some(min_load_factor) => min_load_factor,
+            None => return,
+        };
+        if (self.len as f32) >= self.get_capacity() as f32 * min_load_factor {
+            return;
+        }
+        let capacity = (self.get_capacity() as f32 / self.growth_factor).floor() as usize;
+        let max_load_factor = self.max_load_factor.unwrap_or(1.0);
+        if capacity < self.get_capacity() && (self.len as f32) < capacity as f32 * max_load_factor {
+            self.resize(capacity);
+        }
+    }
+
+    fn grown_capacity(&self, capacity: usize) -> usize {
+        let grown = (capacity as f32 * self.growth_factor).ceil() as usize;
+        grown.max(capacity + 1)
+    }
+
+    /// replace the slots with empty ones of the given capacity, handing
+    /// back the entries of the old slots
+    fn reset(&mut self, capacity: usize) -> Vec<HashTableEntry<K, U>> {
+        let mut data = Vec::with_capacity(capacity);
+        for _i in 0..capacity {
+            data.push(HashTableEntry::<K, U>::default());
+        }
+        self.hops = vec![0; capacity];
+        self.capacity = capacity;
+        self.len = 0;
+        let old = mem::replace(&mut self.data, data);
+        old.into_iter()
+            .filter(|entry| entry.data.is_some())
+            .collect()
+    }
+
+    /// move every entry into a table of the given capacity, growing it
+    /// further should some neighbourhood overflow. Entries keep their
+    /// boxed data, only the slot they live in changes
+    fn resize(&mut self, capacity: usize) {
+        let mut entries = self.reset(capacity);
+        while let Err(mut rest) = self.place_all(entries) {
+            rest.extend(self.reset(self.grown_capacity(self.get_capacity())));
+            entries = rest;
+        }
+    }
+
+    /// place every entry, handing back those not yet placed if one finds
+    /// no room in its neighbourhood
+    fn place_all(
+        &mut self,
+        entries: Vec<HashTableEntry<K, U>>,
+    ) -> Result<(), Vec<HashTableEntry<K, U>>> {
+        let mut entries = entries.into_iter();
+        while let Some(entry) = entries.next() {
+            match self.make_room(&entry.key) {
+                Some(y) => self.place(y, entry.key, entry.data.unwrap()),
+                None => {
+                    let mut rest = vec![entry];
+                    rest.extend(entries);
+                    return Err(rest);
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+pub struct HopscotchHashBuilder<K, U, S = RandomState> {
+    _phantom_k: PhantomData<K>,
+    _phantom_u: PhantomData<U>,
+    capacity: usize,
+    neighbourhood: usize,
+    max_load_factor: Option<f32>,
+    min_load_factor: Option<f32>,
+    growth_factor: f32,
+    hash_builder: S,
+}
+
+impl<K: Default, U> HopscotchHashBuilder<K, U> {
+    pub fn new() -> HopscotchHashBuilder<K, U> {
+        HopscotchHashBuilder::<K, U> {
+            _phantom_k: PhantomData {},
+            _phantom_u: PhantomData {},
+            capacity: 0,
+            neighbourhood: DEFAULT_NEIGHBOURHOOD,
+            max_load_factor: None,
+            min_load_factor: None,
+            growth_factor: DEFAULT_GROWTH_FACTOR,
+            hash_builder: RandomState::new(),
+        }
+    }
+}
+
+impl<K: Default, U> Default for HopscotchHashBuilder<K, U> {
+    fn default() -> HopscotchHashBuilder<K, U> {
+        HopscotchHashBuilder::new()
+    }
+}
+
+impl<K: Default, U, S> HopscotchHashBuilder<K, U, S> {
+    pub fn with_capacity(mut self, capacity: usize) -> HopscotchHashBuilder<K, U, S> {
+        self.capacity = capacity;
+        self
+    }
+
+    /// keep every entry within neighbourhood slots of its home, which
+    /// bounds the slots a lookup looks at. Between 1 and 64, larger
+    /// neighbourhoods let the table fill further before an insert finds
+    /// no room
+    pub fn with_neighbourhood(mut self, neighbourhood: usize) -> HopscotchHashBuilder<K, U, S> {
+        assert!(
+            (1..=MAX_NEIGHBOURHOOD).contains(&neighbourhood),
+            "neighbourhood must be in [1, 64]"
+        );
+        self.neighbourhood = neighbourhood;
+        self
+    }
+
+    /// grow the table once it is more than load_factor full, or an
+    /// insert finds no room in its neighbourhood, instead of failing
+    /// inserts with TableFull. load_factor is in (0, 1]
+    pub fn with_max_load_factor(mut self, load_factor: f32) -> HopscotchHashBuilder<K, U, S> {
+        assert!(
+            load_factor > 0.0 && load_factor <= 1.0,
+            "max load factor must be in (0, 1]"
+        );
+        self.max_load_factor = Some(load_factor);
+        self
+    }
+
+    /// shrink the table once it is less than load_factor full
+    pub fn with_min_load_factor(mut self, load_factor: f32) -> HopscotchHashBuilder<K, U, S> {
+        assert!(
+            (0.0..1.0).contains(&load_factor),
+            "min load factor must be in [0, 1)"
+        );
+        self.min_load_factor = Some(load_factor);
+        self
+    }
+
+    /// factor capacity is multiplied by when growing, and divided by
+    /// when shrinking. Must be greater than 1
+    pub fn with_growth_factor(mut self, growth_factor: f32) -> HopscotchHashBuilder<K, U, S> {
+        assert!(growth_factor > 1.0, "growth factor must be greater than 1");
+        self.growth_factor = growth_factor;
+        self
+    }
+
+    /// hash keys with hash_builder rather than a randomly keyed SipHash.
+    /// Any BuildHasher will do, as will the hashers in hash_table::hashers
+    pub fn with_hasher<T>(self, hash_builder: T) -> HopscotchHashBuilder<K, U, T> {
+        HopscotchHashBuilder::<K, U, T> {
+            _phantom_k: PhantomData {},
+            _phantom_u: PhantomData {},
+            capacity: self.capacity,
+            neighbourhood: self.neighbourhood,
+            max_load_factor: self.max_load_factor,
+            min_load_factor: self.min_load_factor,
+            growth_factor: self.growth_factor,
+            hash_builder,
+        }
+    }
+
+    pub fn build(self) -> HopscotchHash<K, U, S> {
+        let mut hash = HopscotchHash::<K, U, S> {
+            data: Vec::with_capacity(self.capacity),
+            hops: vec![0; self.capacity],
+            capacity: self.capacity,
+            neighbourhood: self.neighbourhood,
+            len: 0,
+            max_load_factor: self.max_load_factor,
+            min_load_factor: self.min_load_factor,
+            growth_factor: self.growth_factor,
+            hash_builder: self.hash_builder,
+        };
+
+        // initialize the hash table
+        for _i in 0..self.capacity {
+            let h = HashTableEntry::<K, U>::default();
+            hash.data.push(h);
+        }
+        hash
+    }
+}
+
+impl<K: Hash + Eq + Default, U, S: HashFn<K>> HashTable<K, U> for HopscotchHash<K, U, S> {
+    fn insert(&mut self, key: K, data: U) -> Result<Option<U>, HashTableError> {
+        match self.entry(key)? {
+            Entry::Occupied(mut entry) => Ok(Some(entry.insert(data))),
+            Entry::Vacant(entry) => {
+                entry.insert(data);
+                Ok(None)
+            }
+        }
+    }
+
+    /// remove clears the slot and its bit in the hop bitmap, lookups
+    /// never probe past it so no tombstone is needed
+    fn remove(&mut self, key: &K) -> Result<U, HashTableError> {
+        let y = self.find_slot(key).ok_or(HashTableError::NotFound)?;
+        Ok(*self.take(y))
+    }
+
+    fn get(&self, key: &K) -> Option<&U> {
+        let y = self.find_slot(key)?;
+        self.data[y].data.as_deref()
+    }
+
+    fn get_mut(&mut self, key: &K) -> Option<&mut U> {
+        let y = self.find_slot(key)?;
+        self.data[y].data.as_deref_mut()
+    }
+
+    fn len(&self) -> usize {
+        self.len
+    }
+
+    fn clear(&mut self) {
+        self.reset(self.get_capacity());
+    }
+
+    fn capacity(&self) -> usize {
+        self.get_capacity()
+    }
+}
+
+impl<K, U, S: HashFn<K>> HopscotchHash<K, U, S> {
+    /// the home slot of key
+    fn hash(&self, key: &K) -> usize {
+        (self.hash_builder.hash(key) % self.get_capacity() as u64) as usize
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use hash_table::hashers::FnvBuildHasher;
+    use hash_table::testing::{check_table, TableBuilder};
+
+    struct Builder;
+
+    impl TableBuilder for Builder {
+        type Table<K: Hash + Eq + Default, U, S: HashFn<K>> = HopscotchHash<K, U, S>;
+
+        fn build<K: Hash + Eq + Default, U, S: HashFn<K>>(
+            &self,
+            hash_builder: S,
+        ) -> HopscotchHash<K, U, S> {
+            HopscotchHashBuilder::new()
+                .with_capacity(8)
+                .with_neighbourhood(4)
+                .with_max_load_factor(0.9)
+                .with_min_load_factor(0.25)
+                .with_hasher(hash_builder)
+                .build()
+        }
+    }
+
+    #[test]
+    fn passes_table_tests() {
+        check_table(Builder);
+    }
+
+    // every entry lies in the neighbourhood of its home, with its bit
+    // set in the home's hop bitmap, and every set bit marks an entry
+    fn check_neighbourhoods<S: HashFn<u16>>(x: &HopscotchHash<u16, u16, S>) {
+        let mut bits = 0;
+        for y in 0..x.get_capacity() {
+            bits += x.hops[y].count_ones() as usize;
+            if x.data[y].data.is_none() {
+                continue;
+            }
+            let home = x.hash(&x.data[y].key);
+            let distance = x.distance(home, y);
+            assert!(distance < x.get_neighbourhood());
+            assert!(x.hops[home] & (1 << distance) != 0);
+        }
+        assert_eq!(bits, x.len);
+    }
+
+    #[test]
+    fn can_lookup_in_neighbourhood_after_delete() {
+        let mut x = HopscotchHashBuilder::<u16, u16>::new()
+            .with_capacity(7)
+            .build();
+
+        // find three keys with the same home slot so they share a bitmap
+        let home = x.hash(&0);
+        let keys: Vec<u16> = (0..u16::MAX)
+            .filter(|k| x.hash(k) == home)
+            .take(3)
+            .collect();
+        for (i, key) in keys.iter().enumerate() {
+            assert!(x.insert(*key, i as u16).is_ok());
+        }
+        assert_eq!(x.hops[home], 0b111);
+
+        assert!(x.delete(&keys[0]).is_ok());
+        assert_eq!(x.hops[home], 0b110);
+        assert_eq!(x.lookup(&keys[2]).unwrap(), 2);
+        assert!(x.delete(&keys[1]).is_ok());
+        assert_eq!(x.lookup(&keys[2]).unwrap(), 2);
+        assert!(x.lookup(&keys[0]).is_err());
+        assert!(x.lookup(&keys[1]).is_err());
+
+        // reinserting fills the first empty slot of the neighbourhood
+        assert!(x.insert(keys[1], 11).is_ok());
+        assert_eq!(x.hops[home], 0b101);
+        assert_eq!(x.lookup(&keys[1]).unwrap(), 11);
+        assert_eq!(x.lookup(&keys[2]).unwrap(), 2);
+        check_neighbourhoods(&x);
+    }
+
+    #[test]
+    fn inserts_hop_entries_into_neighbourhood() {
+        let mut x = HopscotchHashBuilder::<u16, u16>::new()
+            .with_capacity(256)
+            .with_neighbourhood(8)
+            .build();
+        let mut inserted = Vec::new();
+        for key in 0..256 {
+            match x.insert(key, key) {
+                Ok(_) => inserted.push(key),
+                Err(err) => assert!(matches!(err, HashTableError::TableFull)),
+            }
+            check_neighbourhoods(&x);
+        }
+        // displacement lets even a small neighbourhood fill most slots
+        assert!(inserted.len() > 256 * 3 / 4, "{} inserted", inserted.len());
+        for key in 0..256 {
+            let expected = inserted.contains(&key).then_some(&key);
+            assert_eq!(x.get(&key), expected);
+        }
+        for key in inserted.iter().step_by(2) {
+            assert!(x.delete(key).is_ok());
+            check_neighbourhoods(&x);
+        }
+    }
+
+    #[test]
+    fn insert_fails_when_neighbourhood_is_full() {
+        let mut x = HopscotchHashBuilder::<u16, u16>::new()
+            .with_capacity(16)
+            .with_neighbourhood(1)
+            .with_hasher(FnvBuildHasher)
+            .build();
+        // with a neighbourhood of one, a key can only live in its home
+        let home = x.hash(&0);
+        let keys: Vec<u16> = (0..u16::MAX)
+            .filter(|k| x.hash(k) == home)
+            .take(2)
+            .collect();
+        assert!(x.insert(keys[0], 0).is_ok());
+        let ret = x.insert(keys[1], 1);
+        assert!(matches!(ret, Err(HashTableError::TableFull)));
+        assert_eq!(x.len(), 1);
+
+        // growing makes room instead
+        let mut x = HopscotchHashBuilder::<u16, u16>::new()
+            .with_capacity(16)
+            .with_neighbourhood(1)
+            .with_max_load_factor(1.0)
+            .with_hasher(FnvBuildHasher)
+            .build();
+        assert!(x.insert(keys[0], 0).is_ok());
+        assert!(x.insert(keys[1], 1).is_ok());
+        assert!(x.get_capacity() > 16);
+        assert_eq!(x.get(&keys[0]), Some(&0));
+        assert_eq!(x.get(&keys[1]), Some(&1));
+        check_neighbourhoods(&x);
+    }
+
+    // keys whose home is each of the first count slots on from the home
+    // of key 0
+    fn keys_by_home<S: HashFn<u16>>(x: &HopscotchHash<u16, u16, S>, count: usize) -> Vec<Vec<u16>> {
+        let home = x.hash(&0);
+        (0..count)
+            .map(|offset| {
+                (0..u16::MAX)
+                    .filter(|k| x.hash(k) == x.offset(home, offset))
+                    .take(2)
+                    .collect()
+            })
+            .collect()
+    }
+
+    #[test]
+    fn displacement_updates_hop_bitmaps() {
+        let mut x = HopscotchHashBuilder::<u16, u16>::new()
+            .with_capacity(8)
+            .with_neighbourhood(2)
+            .with_hasher(FnvBuildHasher)
+            .build();
+        let home = x.hash(&0);
+        let next = x.offset(home, 1);
+        let keys = keys_by_home(&x, 2);
+        assert!(x.insert(keys[0][0], 0).is_ok());
+        assert!(x.insert(keys[1][0], 1).is_ok());
+        assert_eq!(x.hops[home], 0b01);
+        assert_eq!(x.hops[next], 0b01);
+
+        // the neighbourhood of home is full, so the entry of the next
+        // slot hops on by one to make room
+        assert!(x.insert(keys[0][1], 2).is_ok());
+        assert_eq!(x.data[next].key, keys[0][1]);
+        assert_eq!(x.data[x.offset(home, 2)].key, keys[1][0]);
+        assert_eq!(x.hops[home], 0b11);
+        assert_eq!(x.hops[next], 0b10);
+        check_neighbourhoods(&x);
+        assert_eq!(x.get(&keys[0][0]), Some(&0));
+        assert_eq!(x.get(&keys[1][0]), Some(&1));
+        assert_eq!(x.get(&keys[0][1]), Some(&2));
+    }
+
+    #[test]
+    fn insert_fails_without_hop_candidate() {
+        let mut x = HopscotchHashBuilder::<u16, u16>::new()
+            .with_capacity(8)
+            .with_neighbourhood(2)
+            .with_hasher(FnvBuildHasher)
+            .build();
+        let home = x.hash(&0);
+        let keys = keys_by_home(&x, 2);
+        assert!(x.insert(keys[0][0], 0).is_ok());
+        assert!(x.insert(keys[0][1], 1).is_ok());
+        assert!(x.insert(keys[1][0], 2).is_ok());
+        let hops = x.hops.clone();
+
+        // the nearest empty slot is three on from home, and the entry
+        // before it is already at the edge of its own neighbourhood
+        let key = (0..u16::MAX).filter(|k| x.hash(k) == home).nth(2).unwrap();
+        assert!(matches!(x.insert(key, 3), Err(HashTableError::TableFull)));
+        assert!(matches!(x.entry(key), Err(HashTableError::TableFull)));
+        assert_eq!(x.len(), 3);
+        assert_eq!(x.get_capacity(), 8);
+        assert_eq!(x.hops, hops);
+        check_neighbourhoods(&x);
+    }
+
+    #[test]
+    fn churn_does_not_grow_table() {
+        let mut x = HopscotchHashBuilder::<u16, u16>::new()
+            .with_capacity(8)
+            .with_max_load_factor(0.5)
+            .build();
+        // churn through many keys while holding only a few at a time,
+        // deletes leave no tombstones behind to grow the table for
+        for key in 0..200 {
+            assert!(x.insert(key, key).is_ok());
+            if key >= 2 {
+                assert!(x.delete(&(key - 2)).is_ok());
+            }
+        }
+        assert_eq!(x.get_capacity(), 8);
+        assert_eq!(x.len, 2);
+        assert!(x.lookup(&198).is_ok());
+        assert!(x.lookup(&199).is_ok());
+    }
+}
@@ -0,0 +1,31 @@
+use crate::LinearHash;
+use hash_table::chain;
+use hash_table::entry::EntryTable;
+use hash_table::{HashFn, HashTableError};
+use std::hash::Hash;
+
+/// a view into the chain of a LinearHash for a single key, which is
+/// either occupied by a node holding the key or vacant and ready to
+/// take it
+pub type Entry<'a, K, U> = hash_table::entry::Entry<OccupiedEntry<'a, K, U>, VacantEntry<'a, K, U>>;
+
+/// the link pointing at the node holding the requested key
+pub type OccupiedEntry<'a, K, U> = chain::OccupiedEntry<'a, K, U>;
+
+/// the empty link at the end of the chain the requested key hashes to
+pub type VacantEntry<'a, K, U> = chain::VacantEntry<'a, K, U>;
+
+impl<K: Hash + Eq, U, S: HashFn<K>> EntryTable<K, U> for LinearHash<K, U, S> {
+    type Occupied<'a>
+        = OccupiedEntry<'a, K, U>
+    where
+        Self: 'a;
+    type Vacant<'a>
+        = VacantEntry<'a, K, U>
+    where
+        Self: 'a;
+
+    fn entry(&mut self, key: K) -> Result<Entry<'_, K, U>, HashTableError> {
+        Ok(LinearHash::entry(self, key))
+    }
+}
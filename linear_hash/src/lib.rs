@@ -0,0 +1,426 @@
+#![allow(dead_code)]
+
+use hash_table::{HashFn, HashTable, HashTableError};
+use std::collections::hash_map::RandomState;
+use std::hash::Hash;
+use std::marker::PhantomData;
+
+use std::mem;
+
+mod entry;
+mod iter;
+
+pub use entry::{Entry, OccupiedEntry, VacantEntry};
+pub use hash_table::chain::List;
+pub use hash_table::entry::{Occupied, Vacant};
+pub use iter::{Drain, IntoIter, Iter, IterMut, Keys, Values};
+
+const DEFAULT_MAX_LOAD_FACTOR: f32 = 1.0;
+
+/// a chained table grown by Litwin's linear hashing. Rather than
+/// rehashing every bucket at once, buckets are split one at a time in
+/// order, each split rehashing a single chain into a new bucket at the
+/// end of the table
+pub struct LinearHash<K, U, S = RandomState> {
+    table: Vec<List<K, U>>,
+    // number of buckets the table was built with. Every level doubles
+    // the buckets of the level before
+    initial_capacity: usize,
+    // number of times the table has doubled
+    level: u32,
+    // the next bucket to split. Buckets before it have already split
+    // this level, and address with twice as many buckets
+    split: usize,
+    // number of entries across all chains
+    len: usize,
+    // split a bucket once the average chain length would exceed this
+    max_load_factor: f32,
+    // hashes keys, a key is chained in the bucket its hash modulo the
+    // buckets of the current or next level picks
+    hash_builder: S,
+}
+
+impl<K, U, S> LinearHash<K, U, S> {
+    fn get_capacity(&self) -> usize {
+        self.table.len()
+    }
+
+    /// number of buckets at the start of the current level
+    fn level_capacity(&self) -> usize {
+        self.initial_capacity << self.level
+    }
+
+    /// replace the buckets with empty ones of the given capacity, handing
+    /// back the old buckets. len is left for the caller to account for
+    fn reset(&mut self, capacity: usize) -> Vec<List<K, U>> {
+        let mut table = Vec::with_capacity(capacity);
+        for _i in 0..capacity {
+            table.push(List { head: None });
+        }
+        mem::replace(&mut self.table, table)
+    }
+}
+
+impl<K: Hash + Eq, U, S: HashFn<K>> LinearHash<K, U, S> {
+    /// get the entry for key for in-place manipulation. A vacant entry
+    /// appends to the end of the chain. Buckets are split for an absent
+    /// key up front, an existing key never splits one
+    pub fn entry(&mut self, key: K) -> Entry<'_, K, U> {
+        if self.get(&key).is_none() {
+            self.reserve_one();
+        }
+        let x = self.hash(&key);
+
+        let mut link = &mut self.table[x].head;
+        while link.as_ref().is_some_and(|node| node.data.key != key) {
+            link = &mut link.as_mut().unwrap().next;
+        }
+
+        if link.is_some() {
+            Entry::Occupied(OccupiedEntry::new(link, &mut self.len))
+        } else {
+            Entry::Vacant(VacantEntry::new(link, &mut self.len, key))
+        }
+    }
+}
+
+impl<K: Hash, U, S: HashFn<K>> LinearHash<K, U, S> {
+    /// split buckets until one more entry keeps the average chain
+    /// length within the maximum load factor. That is a single split
+    /// unless the maximum load factor is below 1
+    fn reserve_one(&mut self) {
+        while ((self.len + 1) as f32) > self.get_capacity() as f32 * self.max_load_factor {
+            self.split_one();
+        }
+    }
+
+    /// split the bucket at the split pointer, adding a bucket to the
+    /// end of the table and relinking every node of the split chain
+    /// into whichever of the two it now addresses. Once every bucket of
+    /// the level has split, the next level starts over from bucket 0
+    fn split_one(&mut self) {
+        let mut current = self.table[self.split].head.take();
+        self.table.push(List { head: None });
+        self.split += 1;
+        if self.split == self.level_capacity() {
+            self.level += 1;
+            self.split = 0;
+        }
+        while let Some(mut node) = current {
+            current = node.next.take();
+            let x = self.hash(&node.data.key);
+            node.next = self.table[x].head.take();
+            self.table[x].head = Some(node);
+        }
+    }
+}
+
+pub struct LinearHashBuilder<K, U, S = RandomState> {
+    _phantom_k: PhantomData<K>,
+    _phantom_u: PhantomData<U>,
+    capacity: usize,
+    max_load_factor: f32,
+    hash_builder: S,
+}
+
+impl<K, U> LinearHashBuilder<K, U> {
+    pub fn new() -> LinearHashBuilder<K, U> {
+        LinearHashBuilder::<K, U> {
+            _phantom_k: PhantomData {},
+            _phantom_u: PhantomData {},
+            capacity: 1,
+            max_load_factor: DEFAULT_MAX_LOAD_FACTOR,
+            hash_builder: RandomState::new(),
+        }
+    }
+}
+
+impl<K, U> Default for LinearHashBuilder<K, U> {
+    fn default() -> LinearHashBuilder<K, U> {
+        LinearHashBuilder::new()
+    }
+}
+
+impl<K, U, S> LinearHashBuilder<K, U, S> {
+    /// number of buckets to start with, at least one
+    pub fn with_capacity(mut self, capacity: usize) -> LinearHashBuilder<K, U, S> {
+        assert!(capacity > 0, "capacity must be at least one bucket");
+        self.capacity = capacity;
+        self
+    }
+
+    /// split a bucket once the average chain length, entries divided
+    /// by buckets, would exceed load_factor. 1 by default
+    pub fn with_max_load_factor(mut self, load_factor: f32) -> LinearHashBuilder<K, U, S> {
+        assert!(load_factor > 0.0, "max load factor must be positive");
+        self.max_load_factor = load_factor;
+        self
+    }
+
+    /// hash keys with hash_builder rather than a randomly keyed SipHash.
+    /// Any BuildHasher will do, as will the hashers in hash_table::hashers
+    pub fn with_hasher<T>(self, hash_builder: T) -> LinearHashBuilder<K, U, T> {
+        LinearHashBuilder::<K, U, T> {
+            _phantom_k: PhantomData {},
+            _phantom_u: PhantomData {},
+            capacity: self.capacity,
+            max_load_factor: self.max_load_factor,
+            hash_builder,
+        }
+    }
+
+    pub fn build(self) -> LinearHash<K, U, S> {
+        let mut hash = LinearHash::<K, U, S> {
+            table: Vec::with_capacity(self.capacity),
+            initial_capacity: self.capacity,
+            level: 0,
+            split: 0,
+            len: 0,
+            max_load_factor: self.max_load_factor,
+            hash_builder: self.hash_builder,
+        };
+
+        // initialize the hash table
+        for _i in 0..self.capacity {
+            hash.table.push(List { head: None });
+        }
+        hash
+    }
+}
+
+impl<K: Hash + Eq, U, S: HashFn<K>> HashTable<K, U> for LinearHash<K, U, S> {
+    fn insert(&mut self, key: K, data: U) -> Result<Option<U>, HashTableError> {
+        match self.entry(key) {
+            Entry::Occupied(mut entry) => Ok(Some(entry.insert(data))),
+            Entry::Vacant(entry) => {
+                entry.insert(data);
+                Ok(None)
+            }
+        }
+    }
+
+    /// remove unlinks the node from its chain. Buckets are never
+    /// merged back, the table keeps the buckets it has split
+    fn remove(&mut self, key: &K) -> Result<U, HashTableError> {
+        let x = self.hash(key);
+        let mut current = &mut self.table[x].head;
+        loop {
+            match current {
+                None => break,
+                Some(node) if node.data.key == *key => {
+                    let next = node.next.take();
+                    let node = mem::replace(current, next).unwrap();
+                    self.len -= 1;
+                    return Ok(*node.data.data.unwrap());
+                }
+                Some(node) => {
+                    current = &mut node.next;
+                }
+            }
+        }
+        Err(HashTableError::NotFound)
+    }
+
+    fn get(&self, key: &K) -> Option<&U> {
+        let x = self.hash(key);
+
+        for value in self.table[x].iter().flatten() {
+            if value.data.key == *key {
+                return value.data.data.as_deref();
+            }
+        }
+
+        None
+    }
+
+    fn get_mut(&mut self, key: &K) -> Option<&mut U> {
+        let x = self.hash(key);
+
+        let mut current = &mut self.table[x].head;
+        while let Some(node) = current {
+            if node.data.key == *key {
+                return node.data.data.as_deref_mut();
+            }
+            current = &mut node.next;
+        }
+
+        None
+    }
+
+    fn len(&self) -> usize {
+        self.len
+    }
+
+    fn clear(&mut self) {
+        self.drain();
+    }
+
+    fn capacity(&self) -> usize {
+        self.get_capacity()
+    }
+}
+
+impl<K, U, S: HashFn<K>> LinearHash<K, U, S> {
+    /// the bucket key is chained in. Its hash modulo the buckets of the
+    /// current level picks a bucket, and if that bucket has already
+    /// split, modulo the buckets of the next level picks between it and
+    /// the bucket it split into
+    fn hash(&self, key: &K) -> usize {
+        let hash = self.hash_builder.hash(key);
+        let x = (hash % self.level_capacity() as u64) as usize;
+        if x < self.split {
+            (hash % (2 * self.level_capacity()) as u64) as usize
+        } else {
+            x
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use hash_table::testing::{check_table, TableBuilder};
+
+    struct Builder;
+
+    impl TableBuilder for Builder {
+        type Table<K: Hash + Eq + Default, U, S: HashFn<K>> = LinearHash<K, U, S>;
+
+        fn build<K: Hash + Eq + Default, U, S: HashFn<K>>(
+            &self,
+            hash_builder: S,
+        ) -> LinearHash<K, U, S> {
+            LinearHashBuilder::new()
+                .with_capacity(2)
+                .with_hasher(hash_builder)
+                .build()
+        }
+    }
+
+    #[test]
+    fn passes_table_tests() {
+        check_table(Builder);
+    }
+
+    // every node is chained in the bucket its key addresses, and the
+    // buckets are those of the level plus the ones split so far
+    fn check_addresses<S: HashFn<u16>>(x: &LinearHash<u16, u16, S>) {
+        assert_eq!(x.get_capacity(), x.level_capacity() + x.split);
+        let mut len = 0;
+        for (y, list) in x.table.iter().enumerate() {
+            for node in list.iter().flatten() {
+                assert_eq!(x.hash(&node.data.key), y);
+                len += 1;
+            }
+        }
+        assert_eq!(len, x.len);
+    }
+
+    #[test]
+    fn split_pointer_and_level_progress() {
+        let mut x = LinearHashBuilder::<u16, u16>::new()
+            .with_capacity(2)
+            .build();
+        let mut progress = Vec::new();
+        for key in 0..16 {
+            assert!(x.insert(key, key).is_ok());
+            check_addresses(&x);
+            progress.push((x.level, x.split));
+        }
+        // every key past the first two splits one bucket. The pointer
+        // runs over the 2, 4 then 8 buckets of each level, and the level
+        // goes up as it starts over
+        assert_eq!(
+            progress,
+            vec![
+                (0, 0),
+                (0, 0),
+                (0, 1),
+                (1, 0),
+                (1, 1),
+                (1, 2),
+                (1, 3),
+                (2, 0),
+                (2, 1),
+                (2, 2),
+                (2, 3),
+                (2, 4),
+                (2, 5),
+                (2, 6),
+                (2, 7),
+                (3, 0),
+            ]
+        );
+        assert_eq!(x.get_capacity(), 16);
+        assert_eq!(x.level_capacity(), 16);
+
+        // removing keys merges nothing back
+        for key in 0..16 {
+            assert!(x.delete(&key).is_ok());
+        }
+        assert_eq!((x.level, x.split), (3, 0));
+        assert_eq!(x.get_capacity(), 16);
+    }
+
+    #[test]
+    fn splits_one_bucket_at_a_time() {
+        let mut x = LinearHashBuilder::<u16, u16>::new()
+            .with_capacity(3)
+            .build();
+        for key in 0..1000 {
+            let capacity = x.get_capacity();
+            let split = x.split;
+            assert!(x.insert(key, key).is_ok());
+            // the split pointer moves round the level one bucket per
+            // split, starting over when the level doubles
+            if x.get_capacity() > capacity {
+                assert_eq!(x.get_capacity(), capacity + 1);
+                assert!(x.split == split + 1 || x.split == 0);
+            }
+            assert!(x.len <= x.get_capacity());
+        }
+        assert_eq!(x.get_capacity(), 1000);
+        assert_eq!(x.level, 8);
+        assert_eq!(x.level_capacity(), 768);
+        check_addresses(&x);
+        for key in 0..1000 {
+            assert_eq!(x.get(&key), Some(&key));
+        }
+    }
+
+    #[test]
+    fn splits_enough_buckets_for_low_load_factor() {
+        let mut x = LinearHashBuilder::<u16, u16>::new()
+            .with_max_load_factor(0.25)
+            .build();
+        assert_eq!(x.get_capacity(), 1);
+        for key in 0..100 {
+            assert!(x.insert(key, key).is_ok());
+            assert!(x.len as f32 <= x.get_capacity() as f32 * 0.25);
+            check_addresses(&x);
+        }
+        assert_eq!(x.get_capacity(), 400);
+        for key in 0..100 {
+            assert_eq!(x.get(&key), Some(&key));
+        }
+    }
+
+    #[test]
+    fn updating_existing_key_does_not_split() {
+        let mut x = LinearHashBuilder::<u16, u16>::new()
+            .with_capacity(4)
+            .build();
+        for key in 0..4 {
+            assert!(x.insert(key, key * 10).is_ok());
+        }
+        // the table is at its maximum load, one more key would split
+        for data in 11..100 {
+            assert_eq!(x.insert(1, data).unwrap(), Some(data - 1));
+        }
+        assert_eq!(x.get_capacity(), 4);
+        assert_eq!(x.split, 0);
+        assert!(x.insert(4, 40).is_ok());
+        assert_eq!(x.get_capacity(), 5);
+        assert_eq!(x.split, 1);
+    }
+}
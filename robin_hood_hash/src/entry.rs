@@ -0,0 +1,59 @@
+use crate::RobinHoodHash;
+use hash_table::entry::EntryTable;
+use hash_table::slots::{self, SlotTable};
+use hash_table::{HashFn, HashTableEntry, HashTableError};
+use std::collections::hash_map::RandomState;
+use std::hash::Hash;
+
+/// a view into a single slot of a RobinHoodHash, which is either occupied
+/// by the key it was requested for or vacant and ready to take it
+pub type Entry<'a, K, U, S = RandomState> =
+    hash_table::entry::Entry<OccupiedEntry<'a, K, U, S>, VacantEntry<'a, K, U, S>>;
+
+/// the slot holding the requested key
+pub type OccupiedEntry<'a, K, U, S = RandomState> =
+    slots::OccupiedEntry<'a, RobinHoodHash<K, U, S>>;
+
+/// the slot the requested key will be inserted into, found while
+/// probing for the key, along with the key's probe sequence length
+/// there. Richer entries from that slot on are shifted along to make
+/// room
+pub type VacantEntry<'a, K, U, S = RandomState> = slots::VacantEntry<'a, RobinHoodHash<K, U, S>>;
+
+impl<K: Hash + Eq + Default, U, S: HashFn<K>> SlotTable for RobinHoodHash<K, U, S> {
+    type Key = K;
+    type Data = U;
+    type Vacancy = (usize, usize);
+
+    fn slots(&self) -> &[HashTableEntry<K, U>] {
+        &self.data
+    }
+
+    fn slots_mut(&mut self) -> &mut [HashTableEntry<K, U>] {
+        &mut self.data
+    }
+
+    fn place(&mut self, (y, distance): (usize, usize), key: K, data: Box<U>) -> usize {
+        RobinHoodHash::place(self, y, distance, key, data);
+        y
+    }
+
+    fn take(&mut self, y: usize) -> Box<U> {
+        RobinHoodHash::take(self, y)
+    }
+}
+
+impl<K: Hash + Eq + Default, U, S: HashFn<K>> EntryTable<K, U> for RobinHoodHash<K, U, S> {
+    type Occupied<'a>
+        = OccupiedEntry<'a, K, U, S>
+    where
+        Self: 'a;
+    type Vacant<'a>
+        = VacantEntry<'a, K, U, S>
+    where
+        Self: 'a;
+
+    fn entry(&mut self, key: K) -> Result<Entry<'_, K, U, S>, HashTableError> {
+        RobinHoodHash::entry(self, key)
+    }
+}
@@ -0,0 +1,534 @@
+#![allow(dead_code)]
+
+use hash_table::{HashFn, HashTable, HashTableEntry, HashTableError};
+use std::collections::hash_map::RandomState;
+use std::hash::Hash;
+use std::marker::PhantomData;
+use std::mem;
+
+mod entry;
+mod iter;
+
+pub use entry::{Entry, OccupiedEntry, VacantEntry};
+pub use hash_table::entry::{Occupied, Vacant};
+pub use iter::{Drain, IntoIter, Iter, IterMut, Keys, Values};
+
+const DEFAULT_GROWTH_FACTOR: f32 = 2.0;
+
+pub struct RobinHoodHash<K, U, S = RandomState> {
+    data: Vec<HashTableEntry<K, U>>,
+    // probe sequence length of the entry in each slot, its distance
+    // from its home slot. Inserts take slots from entries closer to
+    // home than themselves, which keeps these lengths even
+    distances: Vec<usize>,
+    capacity: usize,
+    // number of slots holding an entry
+    len: usize,
+    // grow once entries would exceed this fraction of capacity, None
+    // disables growing
+    max_load_factor: Option<f32>,
+    // shrink once entries fall below this fraction of capacity, None
+    // disables shrinking
+    min_load_factor: Option<f32>,
+    // capacity is multiplied by this when growing, divided when shrinking
+    growth_factor: f32,
+    // hashes keys, the home slot of a key is its hash modulo capacity
+    hash_builder: S,
+}
+
+impl<K, U, S> RobinHoodHash<K, U, S> {
+    fn get_capacity(&self) -> usize {
+        self.capacity
+    }
+
+    /// the longest probe sequence of any entry, the most slots a lookup
+    /// looks at past the home slot
+    pub fn max_probe_length(&self) -> usize {
+        self.data
+            .iter()
+            .zip(&self.distances)
+            .filter(|(entry, _)| entry.data.is_some())
+            .map(|(_, distance)| *distance)
+            .max()
+            .unwrap_or(0)
+    }
+
+    fn next(&self, y: usize) -> usize {
+        if y + 1 == self.get_capacity() {
+            0
+        } else {
+            y + 1
+        }
+    }
+}
+
+impl<K: Hash + Eq + Default, U, S: HashFn<K>> RobinHoodHash<K, U, S> {
+    /// get the entry for key for in-place manipulation. Room for an
+    /// absent key is made up front, so this fails with TableFull if the
+    /// key cannot be added. An existing key never resizes the table
+    pub fn entry(&mut self, key: K) -> Result<Entry<'_, K, U, S>, HashTableError> {
+        let mut free = match self.probe(&key) {
+            Ok(slot) => return Ok(Entry::Occupied(OccupiedEntry::new(self, slot))),
+            Err(free) => free,
+        };
+        if self.reserve_one() {
+            free = self.probe(&key).err().flatten();
+        }
+        match free {
+            Some(vacancy) => Ok(Entry::Vacant(VacantEntry::new(self, key, vacancy))),
+            None => Err(HashTableError::TableFull),
+        }
+    }
+}
+
+impl<K: Hash + Eq + Default, U, S: HashFn<K>> RobinHoodHash<K, U, S> {
+    /// find the slot holding key
+    fn find_slot(&self, key: &K) -> Option<usize> {
+        self.probe(key).ok()
+    }
+
+    /// probe for key, returning Ok with the slot holding it, or Err with
+    /// the slot key would be inserted into and its distance from home
+    /// there, None if the table is full. The probe stops early at the
+    /// first entry closer to its home than key would be, as key would
+    /// have taken that entry's slot had it been inserted
+    fn probe(&self, key: &K) -> Result<usize, Option<(usize, usize)>> {
+        if self.get_capacity() == 0 {
+            return Err(None);
+        }
+        let room =
+            |y: usize, distance: usize| (self.len < self.get_capacity()).then_some((y, distance));
+        let mut y = self.hash(key);
+        for distance in 0..self.get_capacity() {
+            if self.data[y].data.is_none() || self.distances[y] < distance {
+                return Err(room(y, distance));
+            }
+            // an entry at a different distance has a different home, so
+            // only keys at the same distance are compared
+            if self.distances[y] == distance && self.data[y].key == *key {
+                return Ok(y);
+            }
+            y = self.next(y);
+        }
+        Err(None)
+    }
+
+    /// insert at slot y, distance from home, shifting the entries from y
+    /// up to the next empty slot along by one. There must be an empty
+    /// slot
+    fn place(&mut self, y: usize, distance: usize, key: K, data: Box<U>) {
+        let mut entry = HashTableEntry {
+            key,
+            data: Some(data),
+        };
+        let mut distance = distance;
+        let mut y = y;
+        while self.data[y].data.is_some() {
+            mem::swap(&mut self.data[y], &mut entry);
+            mem::swap(&mut self.distances[y], &mut distance);
+            distance += 1;
+            y = self.next(y);
+        }
+        self.data[y] = entry;
+        self.distances[y] = distance;
+        self.len += 1;
+    }
+
+    /// empty the occupied slot y and return its data. The entries after
+    /// it that are away from home are shifted back by one, rather than
+    /// leaving a tombstone, so lookups can still stop at an empty slot
+    fn take(&mut self, y: usize) -> Box<U> {
+        let data = self.data[y].data.take().unwrap();
+        let mut y = y;
+        loop {
+            let z = self.next(y);
+            if self.data[z].data.is_none() || self.distances[z] == 0 {
+                break;
+            }
+            self.data[y] = mem::take(&mut self.data[z]);
+            self.distances[y] = self.distances[z] - 1;
+            y = z;
+        }
+        self.distances[y] = 0;
+        self.len -= 1;
+        self.shrink_to_fit_load();
+        data
+    }
+
+    /// grow the table if one more entry would exceed the maximum load
+    /// factor. Returns whether the table grew
+    fn reserve_one(&mut self) -> bool {
+        let max_load_factor = match self.max_load_factor {
+            Some(max_load_factor) => max_load_factor,
+            None => return false,
+        };
+        if ((self.len + 1) as f32) <= self.get_capacity() as f32 * max_load_factor {
+            return false;
+        }
+        let capacity = (self.get_capacity() as f32 * self.growth_factor).ceil() as usize;
+        self.resize(capacity.max(self.get_capacity() + 1));
+        true
+    }
+
+    /// shrink the table if entries have fallen below the minimum load
+    /// factor, as long as the result stays under the maximum load factor
+    fn shrink_to_fit_load(&mut self) {
+        let min_load_factor = match self.min_load_factor {
+            Some(min_load_factor) => min_load_factor,
+            None => return,
+        };
+        if (self.len as f32) >= self.get_capacity() as f32 * min_load_factor {
+            return;
+        }
+        let capacity = (self.get_capacity() as f32 / self.growth_factor).floor() as usize;
+        let max_load_factor = self.max_load_factor.unwrap_or(1.0);
+        if capacity < self.get_capacity() && (self.len as f32) < capacity as f32 * max_load_factor {
+            self.resize(capacity);
+        }
+    }
+
+    /// replace the slots with empty ones of the given capacity, handing
+    /// back the old slots
+    fn reset(&mut self, capacity: usize) -> Vec<HashTableEntry<K, U>> {
+        let mut data = Vec::with_capacity(capacity);
+        for _i in 0..capacity {
+            data.push(HashTableEntry::<K, U>::default());
+        }
+        self.distances = vec![0; capacity];
+        self.capacity = capacity;
+        self.len = 0;
+        mem::replace(&mut self.data, data)
+    }
+
+    /// move every entry into a table of the given capacity. Entries keep
+    /// their boxed data, only the slot they live in changes
+    fn resize(&mut self, capacity: usize) {
+        let old = self.reset(capacity);
+        for entry in old {
+            if let Some(data) = entry.data {
+                // capacity is never below len, and keys are distinct, so
+                // a slot is always found
+                let (y, distance) = self.probe(&entry.key).unwrap_err().unwrap();
+                self.place(y, distance, entry.key, data);
+            }
+        }
+    }
+}
+
+pub struct RobinHoodHashBuilder<K, U, S = RandomState> {
+    _phantom_k: PhantomData<K>,
+    _phantom_u: PhantomData<U>,
+    capacity: usize,
+    max_load_factor: Option<f32>,
+    min_load_factor: Option<f32>,
+    growth_factor: f32,
+    hash_builder: S,
+}
+
+impl<K: Default, U> RobinHoodHashBuilder<K, U> {
+    pub fn new() -> RobinHoodHashBuilder<K, U> {
+        RobinHoodHashBuilder::<K, U> {
+            _phantom_k: PhantomData {},
+            _phantom_u: PhantomData {},
+            capacity: 0,
+            max_load_factor: None,
+            min_load_factor: None,
+            growth_factor: DEFAULT_GROWTH_FACTOR,
+            hash_builder: RandomState::new(),
+        }
+    }
+}
+
+impl<K: Default, U> Default for RobinHoodHashBuilder<K, U> {
+    fn default() -> RobinHoodHashBuilder<K, U> {
+        RobinHoodHashBuilder::new()
+    }
+}
+
+impl<K: Default, U, S> RobinHoodHashBuilder<K, U, S> {
+    pub fn with_capacity(mut self, capacity: usize) -> RobinHoodHashBuilder<K, U, S> {
+        self.capacity = capacity;
+        self
+    }
+
+    /// grow the table once it is more than load_factor full, instead
+    /// of failing inserts with TableFull. load_factor is in (0, 1].
+    /// Robin Hood hashing keeps probes short up to around 0.9
+    pub fn with_max_load_factor(mut self, load_factor: f32) -> RobinHoodHashBuilder<K, U, S> {
+        assert!(
+            load_factor > 0.0 && load_factor <= 1.0,
+            "max load factor must be in (0, 1]"
+        );
+        self.max_load_factor = Some(load_factor);
+        self
+    }
+
+    /// shrink the table once it is less than load_factor full
+    pub fn with_min_load_factor(mut self, load_factor: f32) -> RobinHoodHashBuilder<K, U, S> {
+        assert!(
+            (0.0..1.0).contains(&load_factor),
+            "min load factor must be in [0, 1)"
+        );
+        self.min_load_factor = Some(load_factor);
+        self
+    }
+
+    /// factor capacity is multiplied by when growing, and divided by
+    /// when shrinking. Must be greater than 1
+    pub fn with_growth_factor(mut self, growth_factor: f32) -> RobinHoodHashBuilder<K, U, S> {
+        assert!(growth_factor > 1.0, "growth factor must be greater than 1");
+        self.growth_factor = growth_factor;
+        self
+    }
+
+    /// hash keys with hash_builder rather than a randomly keyed SipHash.
+    /// Any BuildHasher will do, as will the hashers in hash_table::hashers
+    pub fn with_hasher<T>(self, hash_builder: T) -> RobinHoodHashBuilder<K, U, T> {
+        RobinHoodHashBuilder::<K, U, T> {
+            _phantom_k: PhantomData {},
+            _phantom_u: PhantomData {},
+            capacity: self.capacity,
+            max_load_factor: self.max_load_factor,
+            min_load_factor: self.min_load_factor,
+            growth_factor: self.growth_factor,
+            hash_builder,
+        }
+    }
+
+    pub fn build(self) -> RobinHoodHash<K, U, S> {
+        let mut hash = RobinHoodHash::<K, U, S> {
+            data: Vec::with_capacity(self.capacity),
+            distances: vec![0; self.capacity],
+            capacity: self.capacity,
+            len: 0,
+            max_load_factor: self.max_load_factor,
+            min_load_factor: self.min_load_factor,
+            growth_factor: self.growth_factor,
+            hash_builder: self.hash_builder,
+        };
+
+        // initialize the hash table
+        for _i in 0..self.capacity {
+            let h = HashTableEntry::<K, U>::default();
+            hash.data.push(h);
+        }
+        hash
+    }
+}
+
+impl<K: Hash + Eq + Default, U, S: HashFn<K>> HashTable<K, U> for RobinHoodHash<K, U, S> {
+    fn insert(&mut self, key: K, data: U) -> Result<Option<U>, HashTableError> {
+        match self.entry(key)? {
+            Entry::Occupied(mut entry) => Ok(Some(entry.insert(data))),
+            Entry::Vacant(entry) => {
+                entry.insert(data);
+                Ok(None)
+            }
+        }
+    }
+
+    /// remove clears the slot and shifts the rest of its cluster back,
+    /// so no tombstones build up
+    fn remove(&mut self, key: &K) -> Result<U, HashTableError> {
+        let y = self.find_slot(key).ok_or(HashTableError::NotFound)?;
+        Ok(*self.take(y))
+    }
+
+    fn get(&self, key: &K) -> Option<&U> {
+        let y = self.find_slot(key)?;
+        self.data[y].data.as_deref()
+    }
+
+    fn get_mut(&mut self, key: &K) -> Option<&mut U> {
+        let y = self.find_slot(key)?;
+        self.data[y].data.as_deref_mut()
+    }
+
+    fn len(&self) -> usize {
+        self.len
+    }
+
+    fn clear(&mut self) {
+        self.reset(self.get_capacity());
+    }
+
+    fn capacity(&self) -> usize {
+        self.get_capacity()
+    }
+}
+
+impl<K, U, S: HashFn<K>> RobinHoodHash<K, U, S> {
+    /// the home slot of key
+    fn hash(&self, key: &K) -> usize {
+        (self.hash_builder.hash(key) % self.get_capacity() as u64) as usize
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use hash_table::testing::{check_table, TableBuilder};
+
+    struct Builder;
+
+    impl TableBuilder for Builder {
+        type Table<K: Hash + Eq + Default, U, S: HashFn<K>> = RobinHoodHash<K, U, S>;
+
+        fn build<K: Hash + Eq + Default, U, S: HashFn<K>>(
+            &self,
+            hash_builder: S,
+        ) -> RobinHoodHash<K, U, S> {
+            RobinHoodHashBuilder::new()
+                .with_capacity(8)
+                .with_max_load_factor(0.9)
+                .with_min_load_factor(0.25)
+                .with_hasher(hash_builder)
+                .build()
+        }
+    }
+
+    #[test]
+    fn passes_table_tests() {
+        check_table(Builder);
+    }
+
+    // every entry sits its recorded distance from home, and no entry
+    // is more than one slot further from home than the one before it
+    fn check_probe_lengths<S: HashFn<u16>>(x: &RobinHoodHash<u16, u16, S>) {
+        let capacity = x.get_capacity();
+        for y in 0..capacity {
+            if x.data[y].data.is_none() {
+                continue;
+            }
+            let home = x.hash(&x.data[y].key);
+            assert_eq!(x.distances[y], (y + capacity - home) % capacity);
+            let z = x.next(y);
+            if x.data[z].data.is_some() {
+                assert!(x.distances[z] <= x.distances[y] + 1);
+            } else {
+                assert_eq!(x.distances[z], 0);
+            }
+        }
+    }
+
+    #[test]
+    fn deletion_shifts_cluster_back() {
+        let mut x = RobinHoodHashBuilder::<u16, u16>::new()
+            .with_capacity(7)
+            .build();
+
+        // find three keys with the same home slot so they form one cluster
+        let home = x.hash(&0);
+        let keys: Vec<u16> = (0..u16::MAX)
+            .filter(|k| x.hash(k) == home)
+            .take(3)
+            .collect();
+        for (i, key) in keys.iter().enumerate() {
+            assert!(x.insert(*key, i as u16).is_ok());
+        }
+        assert_eq!(x.distances[(home + 2) % 7], 2);
+        check_probe_lengths(&x);
+
+        // deleting the head moves the rest of the cluster one slot back,
+        // leaving the slot after it empty
+        assert!(x.delete(&keys[0]).is_ok());
+        assert_eq!(x.data[home].key, keys[1]);
+        assert_eq!(x.data[(home + 1) % 7].key, keys[2]);
+        assert!(x.data[(home + 2) % 7].data.is_none());
+        assert_eq!(x.max_probe_length(), 1);
+        check_probe_lengths(&x);
+        assert_eq!(x.lookup(&keys[2]).unwrap(), 2);
+        assert!(x.lookup(&keys[0]).is_err());
+
+        assert!(x.delete(&keys[1]).is_ok());
+        assert_eq!(x.max_probe_length(), 0);
+        assert_eq!(x.lookup(&keys[2]).unwrap(), 2);
+        assert!(x.insert(keys[1], 11).is_ok());
+        assert_eq!(x.lookup(&keys[1]).unwrap(), 11);
+        assert_eq!(x.lookup(&keys[2]).unwrap(), 2);
+        check_probe_lengths(&x);
+    }
+
+    #[test]
+    fn probe_lengths_are_tracked() {
+        let mut x = RobinHoodHashBuilder::<u16, u16>::new()
+            .with_capacity(64)
+            .build();
+        for key in 0..60 {
+            assert!(x.insert(key, key).is_ok());
+            check_probe_lengths(&x);
+        }
+        for key in (0..60).step_by(3) {
+            assert!(x.delete(&key).is_ok());
+            check_probe_lengths(&x);
+        }
+        for key in 0..60 {
+            let expected = (key % 3 != 0).then_some(&key);
+            assert_eq!(x.get(&key), expected);
+        }
+    }
+
+    #[test]
+    fn probe_lengths_stay_short_at_high_load() {
+        let mut x = RobinHoodHashBuilder::<u16, u16>::new()
+            .with_capacity(4096)
+            .build();
+        let keys = 4096 * 9 / 10;
+        for key in 0..keys {
+            assert!(x.insert(key, key).is_ok());
+        }
+        let total: usize = x
+            .data
+            .iter()
+            .zip(&x.distances)
+            .filter(|(entry, _)| entry.data.is_some())
+            .map(|(_, distance)| *distance)
+            .sum();
+        // linear probing averages about 4.5 at this load either way, Robin
+        // Hood keeps the longest probe within a small multiple of that
+        let mean = total as f32 / keys as f32;
+        assert!(mean < 10.0, "mean probe length {}", mean);
+        assert!(x.max_probe_length() < 128, "{}", x.max_probe_length());
+        for key in 0..keys {
+            assert_eq!(x.get(&key), Some(&key));
+        }
+        assert!(x.get(&keys).is_none());
+    }
+
+    #[test]
+    fn churn_does_not_grow_table() {
+        let mut x = RobinHoodHashBuilder::<u16, u16>::new()
+            .with_capacity(8)
+            .with_max_load_factor(0.5)
+            .build();
+        // churn through many keys while holding only a few at a time,
+        // deletes leave no tombstones behind to grow the table for
+        for key in 0..200 {
+            assert!(x.insert(key, key).is_ok());
+            if key >= 2 {
+                assert!(x.delete(&(key - 2)).is_ok());
+            }
+        }
+        assert_eq!(x.get_capacity(), 8);
+        assert_eq!(x.len, 2);
+        assert!(x.lookup(&198).is_ok());
+        assert!(x.lookup(&199).is_ok());
+    }
+
+    #[test]
+    fn updating_existing_key_does_not_resize() {
+        let mut x = RobinHoodHashBuilder::<u16, u16>::new()
+            .with_capacity(4)
+            .with_max_load_factor(0.5)
+            .build();
+        assert!(x.insert(1, 10).is_ok());
+        assert!(x.insert(2, 20).is_ok());
+        // the table is at its maximum load, one more key would grow it
+        for data in 11..20 {
+            assert_eq!(x.insert(1, data).unwrap(), Some(data - 1));
+        }
+        assert_eq!(x.get_capacity(), 4);
+        assert!(x.insert(3, 30).is_ok());
+        assert!(x.get_capacity() > 4);
+    }
+}
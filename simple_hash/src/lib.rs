@@ -1,134 +1,494 @@
 #![allow(dead_code)]
 
 use hash_table::{HashFn, HashTable, HashTableEntry, HashTableError};
+use std::collections::hash_map::RandomState;
+use std::hash::Hash;
 use std::marker::PhantomData;
 
-struct BasicHash<U> {
-    data: Vec<HashTableEntry<U>>,
-    capacity: u16,
+mod entry;
+mod iter;
+
+pub use entry::{Entry, OccupiedEntry, VacantEntry};
+pub use hash_table::entry::{Occupied, Vacant};
+pub use iter::{Drain, IntoIter, Iter, IterMut, Keys, Values};
+
+const DEFAULT_GROWTH_FACTOR: f32 = 2.0;
+
+/// the order slots are probed in, starting from the home slot of a key
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum ProbeSequence {
+    /// step to the next slot, wrapping around. Cheap and cache friendly,
+    /// but keys pile up in long runs of full slots
+    Linear,
+    /// step 1, 2, 3, ... slots further each time, so the slots probed
+    /// are a triangular number of slots from home. This visits every
+    /// slot only when capacity is a power of two, so capacities are
+    /// rounded to one
+    Quadratic,
+    /// step by a second hash of the key, so keys sharing a home slot
+    /// part ways straight after it. The step shares no factor with
+    /// capacity, so every slot is visited
+    DoubleHashing,
+}
+
+/// the slots of a table in the order a ProbeSequence visits them,
+/// each exactly once
+struct Probes {
+    sequence: ProbeSequence,
+    capacity: usize,
+    // the next slot probed
+    y: usize,
+    // the double hashing step
+    step: usize,
+    // slots probed so far
+    probed: usize,
+}
+
+impl Iterator for Probes {
+    type Item = usize;
+
+    fn next(&mut self) -> Option<usize> {
+        if self.probed == self.capacity {
+            return None;
+        }
+        let y = self.y;
+        self.probed += 1;
+        let step = match self.sequence {
+            ProbeSequence::Linear => 1,
+            ProbeSequence::Quadratic => self.probed,
+            ProbeSequence::DoubleHashing => self.step,
+        };
+        self.y = (y + step) % self.capacity;
+        Some(y)
+    }
+}
+
+fn gcd(mut a: u64, mut b: u64) -> u64 {
+    while b != 0 {
+        (a, b) = (b, a % b);
+    }
+    a
 }
 
-impl<U> BasicHash<U> {
-    fn get_capacity(&self) -> u16 {
+/// a double hashing step in [1, capacity) taken from hash, lowered
+/// until it shares no factor with capacity
+fn coprime_step(hash: u64, capacity: u64) -> usize {
+    if capacity <= 2 {
+        return 1;
+    }
+    let mut step = 1 + hash % (capacity - 1);
+    while gcd(step, capacity) != 1 {
+        step -= 1;
+    }
+    step as usize
+}
+
+pub struct BasicHash<K, U, S = RandomState> {
+    data: Vec<HashTableEntry<K, U>>,
+    // tombstones, true for slots whose entry was deleted. Probing
+    // continues past these so keys that collided with the deleted
+    // entry can still be found
+    deleted: Vec<bool>,
+    capacity: usize,
+    // number of slots holding an entry
+    len: usize,
+    // number of slots marked deleted
+    tombstones: usize,
+    // grow once entries plus tombstones would exceed this fraction of
+    // capacity, None disables growing
+    max_load_factor: Option<f32>,
+    // shrink once entries fall below this fraction of capacity, None
+    // disables shrinking
+    min_load_factor: Option<f32>,
+    // capacity is multiplied by this when growing, divided when shrinking
+    growth_factor: f32,
+    // the order slots are probed in, shared by insert, lookup and delete
+    probe_sequence: ProbeSequence,
+    // hashes keys, the home slot of a key is its hash modulo capacity
+    hash_builder: S,
+}
+
+impl<K, U, S> BasicHash<K, U, S> {
+    fn get_capacity(&self) -> usize {
         self.capacity
     }
+
+    /// capacity to build or grow to, rounded up to a power of two if
+    /// the probe sequence needs one
+    fn fit_capacity(&self, capacity: usize) -> usize {
+        fit_capacity(self.probe_sequence, capacity)
+    }
+}
+
+fn fit_capacity(sequence: ProbeSequence, capacity: usize) -> usize {
+    match sequence {
+        ProbeSequence::Quadratic if capacity > 0 => capacity.next_power_of_two(),
+        _ => capacity,
+    }
+}
+
+impl<K: Hash + Eq + Default, U, S: HashFn<K>> BasicHash<K, U, S> {
+    /// get the entry for key for in-place manipulation. Room for an
+    /// absent key is made up front, so this fails with TableFull if the
+    /// key cannot be added. An existing key never resizes the table
+    pub fn entry(&mut self, key: K) -> Result<Entry<'_, K, U, S>, HashTableError> {
+        let mut free = match self.probe(&key) {
+            Ok(slot) => return Ok(Entry::Occupied(OccupiedEntry::new(self, slot))),
+            Err(free) => free,
+        };
+        if self.reserve_one() {
+            free = self.find_empty_slot(&key);
+        }
+        match free {
+            Some(slot) => Ok(Entry::Vacant(VacantEntry::new(self, key, slot))),
+            None => Err(HashTableError::TableFull),
+        }
+    }
+}
+
+impl<K: Hash + Eq + Default, U, S: HashFn<K>> BasicHash<K, U, S> {
+    /// find the slot holding key, probing past deleted slots and
+    /// stopping at the first slot that has never been used
+    fn find_slot(&self, key: &K) -> Option<usize> {
+        self.probe(key).ok()
+    }
+
+    /// probe for key, returning Ok with the slot holding it, or Err with
+    /// the first empty slot seen along the way, which is where key would
+    /// be inserted
+    fn probe(&self, key: &K) -> Result<usize, Option<usize>> {
+        let mut empty = None;
+        for y in self.probes(key) {
+            if self.data[y].data.is_some() {
+                if self.data[y].key == *key {
+                    return Ok(y);
+                }
+            } else if !self.deleted[y] {
+                return Err(empty.or(Some(y)));
+            } else if empty.is_none() {
+                empty = Some(y);
+            }
+        }
+        Err(empty)
+    }
+
+    /// find the first empty slot along the probe sequence of key,
+    /// deleted slots are empty and get reused
+    fn find_empty_slot(&self, key: &K) -> Option<usize> {
+        self.probes(key).find(|y| self.data[*y].data.is_none())
+    }
+
+    fn place(&mut self, y: usize, key: K, data: Box<U>) {
+        if self.deleted[y] {
+            self.deleted[y] = false;
+            self.tombstones -= 1;
+        }
+        self.data[y].key = key;
+        self.data[y].data = Some(data);
+        self.len += 1;
+    }
+
+    /// empty the occupied slot y, leaving a tombstone behind, and
+    /// return its data
+    fn take(&mut self, y: usize) -> Box<U> {
+        let data = self.data[y].data.take().unwrap();
+        self.deleted[y] = true;
+        self.len -= 1;
+        self.tombstones += 1;
+        self.shrink_to_fit_load();
+        data
+    }
+
+    /// grow the table, or rehash it in place to drop tombstones, if
+    /// one more entry would exceed the maximum load factor. Returns
+    /// whether the table was rebuilt
+    fn reserve_one(&mut self) -> bool {
+        let max_load_factor = match self.max_load_factor {
+            Some(max_load_factor) => max_load_factor,
+            None => return false,
+        };
+        let limit = self.get_capacity() as f32 * max_load_factor;
+        if ((self.len + self.tombstones + 1) as f32) <= limit {
+            return false;
+        }
+        if ((self.len + 1) as f32) <= limit {
+            self.resize(self.get_capacity());
+        } else {
+            let capacity = (self.get_capacity() as f32 * self.growth_factor).ceil() as usize;
+            self.resize(self.fit_capacity(capacity.max(self.get_capacity() + 1)));
+        }
+        true
+    }
+
+    /// shrink the table if entries have fallen below the minimum load
+    /// factor, as long as the result stays under the maximum load factor
+    fn shrink_to_fit_load(&mut self) {
+        let min_load_factor = match self.min_load_factor {
+            Some(min_load_factor) => min_load_factor,
+            None => return,
+        };
+        if (self.len as f32) >= self.get_capacity() as f32 * min_load_factor {
+            return;
+        }
+        let capacity = (self.get_capacity() as f32 / self.growth_factor).floor() as usize;
+        let capacity = match self.probe_sequence {
+            // round down, as rounding up could land back on the current
+            // capacity
+            ProbeSequence::Quadratic if capacity > 0 => 1 << capacity.ilog2(),
+            _ => capacity,
+        };
+        let max_load_factor = self.max_load_factor.unwrap_or(1.0);
+        if capacity < self.get_capacity() && (self.len as f32) < capacity as f32 * max_load_factor {
+            self.resize(capacity);
+        }
+    }
+
+    /// replace the slots with empty ones of the given capacity, handing
+    /// back the old slots
+    fn reset(&mut self, capacity: usize) -> Vec<HashTableEntry<K, U>> {
+        let mut data = Vec::with_capacity(capacity);
+        for _i in 0..capacity {
+            data.push(HashTableEntry::<K, U>::default());
+        }
+        self.deleted = vec![false; capacity];
+        self.capacity = capacity;
+        self.len = 0;
+        self.tombstones = 0;
+        std::mem::replace(&mut self.data, data)
+    }
+
+    /// move every entry into a table of the given capacity, dropping
+    /// all tombstones. Entries keep their boxed data, only the slot
+    /// they live in changes
+    fn resize(&mut self, capacity: usize) {
+        let old = self.reset(capacity);
+        for entry in old {
+            if let Some(data) = entry.data {
+                // capacity is never below len, so a slot is always found
+                let y = self.find_empty_slot(&entry.key).unwrap();
+                self.place(y, entry.key, data);
+            }
+        }
+    }
 }
 
-struct BasicHashBuilder<U> {
+pub struct BasicHashBuilder<K, U, S = RandomState> {
+    _phantom_k: PhantomData<K>,
     _phantom_u: PhantomData<U>,
-    capacity: u16,
+    capacity: usize,
+    max_load_factor: Option<f32>,
+    min_load_factor: Option<f32>,
+    growth_factor: f32,
+    probe_sequence: ProbeSequence,
+    hash_builder: S,
 }
 
-impl<U: Default> BasicHashBuilder<U> {
-    fn new() -> BasicHashBuilder<U> {
-        BasicHashBuilder::<U> {
+impl<K: Default, U> BasicHashBuilder<K, U> {
+    pub fn new() -> BasicHashBuilder<K, U> {
+        BasicHashBuilder::<K, U> {
+            _phantom_k: PhantomData {},
             _phantom_u: PhantomData {},
             capacity: 0,
+            max_load_factor: None,
+            min_load_factor: None,
+            growth_factor: DEFAULT_GROWTH_FACTOR,
+            probe_sequence: ProbeSequence::Linear,
+            hash_builder: RandomState::new(),
         }
     }
+}
 
-    pub fn with_capacity(mut self, capacity: u16) -> BasicHashBuilder<U> {
+impl<K: Default, U> Default for BasicHashBuilder<K, U> {
+    fn default() -> BasicHashBuilder<K, U> {
+        BasicHashBuilder::new()
+    }
+}
+
+impl<K: Default, U, S> BasicHashBuilder<K, U, S> {
+    pub fn with_capacity(mut self, capacity: usize) -> BasicHashBuilder<K, U, S> {
         self.capacity = capacity;
         self
     }
 
-    pub fn build(self) -> BasicHash<U> {
-        let mut hash = BasicHash::<U> {
-            data: Vec::with_capacity(self.capacity.into()),
+    /// grow the table once it is more than load_factor full, instead
+    /// of failing inserts with TableFull. load_factor is in (0, 1]
+    pub fn with_max_load_factor(mut self, load_factor: f32) -> BasicHashBuilder<K, U, S> {
+        assert!(
+            load_factor > 0.0 && load_factor <= 1.0,
+            "max load factor must be in (0, 1]"
+        );
+        self.max_load_factor = Some(load_factor);
+        self
+    }
+
+    /// shrink the table once it is less than load_factor full
+    pub fn with_min_load_factor(mut self, load_factor: f32) -> BasicHashBuilder<K, U, S> {
+        assert!(
+            (0.0..1.0).contains(&load_factor),
+            "min load factor must be in [0, 1)"
+        );
+        self.min_load_factor = Some(load_factor);
+        self
+    }
+
+    /// factor capacity is multiplied by when growing, and divided by
+    /// when shrinking. Must be greater than 1
+    pub fn with_growth_factor(mut self, growth_factor: f32) -> BasicHashBuilder<K, U, S> {
+        assert!(growth_factor > 1.0, "growth factor must be greater than 1");
+        self.growth_factor = growth_factor;
+        self
+    }
+
+    /// the order slots are probed in, linear by default. Quadratic
+    /// probing rounds capacity up to a power of two
+    pub fn with_probe_sequence(mut self, sequence: ProbeSequence) -> BasicHashBuilder<K, U, S> {
+        self.probe_sequence = sequence;
+        self
+    }
+
+    /// hash keys with hash_builder rather than a randomly keyed SipHash.
+    /// Any BuildHasher will do, as will the hashers in hash_table::hashers
+    pub fn with_hasher<T>(self, hash_builder: T) -> BasicHashBuilder<K, U, T> {
+        BasicHashBuilder::<K, U, T> {
+            _phantom_k: PhantomData {},
+            _phantom_u: PhantomData {},
             capacity: self.capacity,
+            max_load_factor: self.max_load_factor,
+            min_load_factor: self.min_load_factor,
+            growth_factor: self.growth_factor,
+            probe_sequence: self.probe_sequence,
+            hash_builder,
+        }
+    }
+
+    pub fn build(self) -> BasicHash<K, U, S> {
+        let capacity = fit_capacity(self.probe_sequence, self.capacity);
+        let mut hash = BasicHash::<K, U, S> {
+            data: Vec::with_capacity(capacity),
+            deleted: vec![false; capacity],
+            capacity,
+            len: 0,
+            tombstones: 0,
+            max_load_factor: self.max_load_factor,
+            min_load_factor: self.min_load_factor,
+            growth_factor: self.growth_factor,
+            probe_sequence: self.probe_sequence,
+            hash_builder: self.hash_builder,
         };
 
         // initialize the hash table
-        for _i in 0..self.capacity {
-            let h = HashTableEntry::<U>::default();
+        for _i in 0..capacity {
+            let h = HashTableEntry::<K, U>::default();
             hash.data.push(h);
         }
         hash
     }
 }
 
-impl<U: std::marker::Copy + std::fmt::Debug> HashTable<U> for BasicHash<U> {
-    fn insert(&mut self, key: u16, data: U) -> Result<(), HashTableError> {
-        let x : usize = self.hash(key).into();
-        if self.data[x].data.is_none() {
-            self.data[x].key = key;
-            self.data[x].data = Some(Box::new(data));
-        } else {
-            let mut y = x + 1;
-            if y == self.get_capacity().into() {
-                y = 0;
-            }
-            let mut inserted = false;
-            while inserted == false && y != x {
-                if self.data[y].data.is_none() {
-                    self.data[y].key = key;
-                    self.data[y].data = Some(Box::new(data));
-                    inserted = true;
-                    break;
-                } else {
-                    y = y + 1;
-                    if y == self.get_capacity().into() {
-                        y = 0;
-                    }
-                }
-            }
-            if inserted == false {
-                return Err(HashTableError::TableFull);
+impl<K: Hash + Eq + Default, U, S: HashFn<K>> HashTable<K, U> for BasicHash<K, U, S> {
+    fn insert(&mut self, key: K, data: U) -> Result<Option<U>, HashTableError> {
+        match self.entry(key)? {
+            Entry::Occupied(mut entry) => Ok(Some(entry.insert(data))),
+            Entry::Vacant(entry) => {
+                entry.insert(data);
+                Ok(None)
             }
         }
-        Ok(())
     }
-    fn delete(&mut self, _key: u16) -> Result<(), HashTableError> {
-        // deletion in hash tables of this type requires that all 
-        // entries that would have collided with the entry being
-        // deleted be reinserted. Otherwise, a search for one of
-        // these colliding keys would fail by returning early as
-        // it encounters an empty entry. A hash table better 
-        // suited to deletions would be chained-hash.
 
-        unimplemented!();
+    /// remove clears the slot and marks it as a tombstone, rather than
+    /// reinserting every entry that would have collided with it
+    fn remove(&mut self, key: &K) -> Result<U, HashTableError> {
+        let y = self.find_slot(key).ok_or(HashTableError::NotFound)?;
+        Ok(*self.take(y))
     }
-    fn lookup(&self, key: u16) -> Result<U, HashTableError> 
-        where U : Copy {
-        let x : usize = self.hash(key).into();
-        if self.data[x].data.is_none() {
-            return Err(HashTableError::NotFound);
-        }
-        if self.data[x].key == key {
-            return Ok(**self.data[x].data.as_ref().unwrap());
-        } else {
-            let mut y = x+1;
-            if y == self.get_capacity().into() {
-                y = 0;
-            }
-            while y != x && self.data[x].data.is_some() && self.data[y].key != key {
-                y += 1;
-                if y == self.get_capacity().into() {
-                    y = 0;
-                }
-            }
-            if self.data[y].data.is_some() && self.data[y].key == key {
-                return Ok(**self.data[y].data.as_ref().unwrap());
-            }
-        }
-        return Err(HashTableError::NotFound);
+
+    fn get(&self, key: &K) -> Option<&U> {
+        let y = self.find_slot(key)?;
+        self.data[y].data.as_deref()
+    }
+
+    fn get_mut(&mut self, key: &K) -> Option<&mut U> {
+        let y = self.find_slot(key)?;
+        self.data[y].data.as_deref_mut()
+    }
+
+    fn len(&self) -> usize {
+        self.len
+    }
+
+    fn clear(&mut self) {
+        self.reset(self.get_capacity());
+    }
+
+    fn capacity(&self) -> usize {
+        self.get_capacity()
     }
 }
 
-//impl<T, U, const N: T> HashFn<T> for BasicHash<T, U, N> where T: std::ops::Rem<usize, Output = usize> {
-impl<U> HashFn for BasicHash<U> {
-    fn hash(&self, key: u16) -> u16 {
-        key % self.get_capacity()
+impl<K, U, S: HashFn<K>> BasicHash<K, U, S> {
+    /// the home slot of key
+    fn hash(&self, key: &K) -> usize {
+        (self.hash_builder.hash(key) % self.get_capacity() as u64) as usize
+    }
+
+    /// the slots probed for key, starting at its home slot. Double
+    /// hashing takes its step from the hash bits left over once the
+    /// home slot is picked
+    fn probes(&self, key: &K) -> Probes {
+        let capacity = self.get_capacity();
+        let mut probes = Probes {
+            sequence: self.probe_sequence,
+            capacity,
+            y: 0,
+            step: 1,
+            probed: 0,
+        };
+        if capacity > 0 {
+            let hash = self.hash_builder.hash(key);
+            probes.y = (hash % capacity as u64) as usize;
+            if self.probe_sequence == ProbeSequence::DoubleHashing {
+                probes.step = coprime_step(hash / capacity as u64, capacity as u64);
+            }
+        }
+        probes
     }
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
+    use hash_table::hashers::MultiplyShift;
+    use hash_table::testing::{check_table, TableBuilder};
+
+    struct Builder(ProbeSequence);
+
+    impl TableBuilder for Builder {
+        type Table<K: Hash + Eq + Default, U, S: HashFn<K>> = BasicHash<K, U, S>;
+
+        fn build<K: Hash + Eq + Default, U, S: HashFn<K>>(
+            &self,
+            hash_builder: S,
+        ) -> BasicHash<K, U, S> {
+            BasicHashBuilder::new()
+                .with_capacity(8)
+                .with_max_load_factor(0.75)
+                .with_probe_sequence(self.0)
+                .with_hasher(hash_builder)
+                .build()
+        }
+    }
+
+    #[test]
+    fn passes_table_tests() {
+        check_table(Builder(ProbeSequence::Linear));
+        check_table(Builder(ProbeSequence::Quadratic));
+        check_table(Builder(ProbeSequence::DoubleHashing));
+    }
 
     #[test]
     fn can_create_basic_hash() {
-        let mut x = BasicHashBuilder::<u16>::new().with_capacity(3).build();
+        let mut x = BasicHashBuilder::<u16, u16>::new().with_capacity(3).build();
         assert_eq!(x.get_capacity(), 3);
         let mut item = 10;
         assert!(x.insert(1, item).is_ok());
@@ -137,12 +497,12 @@ mod tests {
         item += 10;
         assert!(x.insert(3, item).is_ok());
         item += 10;
-        assert_eq!(x.insert(4, item).is_ok(), false);
+        assert!(x.insert(4, item).is_err());
     }
-        
+
     #[test]
     fn can_create_basic_hash_and_search() {
-        let mut x = BasicHashBuilder::<u16>::new().with_capacity(3).build();
+        let mut x = BasicHashBuilder::<u16, u16>::new().with_capacity(3).build();
         assert_eq!(x.get_capacity(), 3);
         let mut item = 10;
         assert!(x.insert(1, item).is_ok());
@@ -150,17 +510,330 @@ mod tests {
         assert!(x.insert(2, item).is_ok());
         item += 10;
         assert!(x.insert(3, item).is_ok());
-    
-        let ret = x.lookup(1);
+
+        let ret = x.lookup(&1);
         assert!(ret.is_ok());
         assert_eq!(ret.unwrap(), 10);
-        let ret = x.lookup(2);
+        let ret = x.lookup(&2);
         assert!(ret.is_ok());
         assert_eq!(ret.unwrap(), 20);
-        let ret = x.lookup(3);
+        let ret = x.lookup(&3);
+        assert!(ret.is_ok());
+        assert_eq!(ret.unwrap(), 30);
+        let ret = x.lookup(&17);
+        assert!(ret.is_err());
+    }
+
+    #[test]
+    fn can_create_basic_hash_and_delete() {
+        let mut x = BasicHashBuilder::<u16, u16>::new().with_capacity(3).build();
+        assert_eq!(x.get_capacity(), 3);
+        let mut item = 10;
+        assert!(x.insert(1, item).is_ok());
+        item += 10;
+        assert!(x.insert(2, item).is_ok());
+        item += 10;
+        assert!(x.insert(3, item).is_ok());
+
+        let ret = x.delete(&2);
+        assert!(ret.is_ok());
+        let ret = x.lookup(&2);
+        assert!(ret.is_err());
+        let ret = x.delete(&2);
+        assert!(ret.is_err());
+
+        // the table is full, so every key is part of one cluster
+        let ret = x.lookup(&1);
+        assert!(ret.is_ok());
+        assert_eq!(ret.unwrap(), 10);
+        let ret = x.lookup(&3);
         assert!(ret.is_ok());
         assert_eq!(ret.unwrap(), 30);
-        let ret = x.lookup(17);
-        assert_eq!(ret.is_ok(), false);
+
+        // the deleted slot can be reused
+        assert!(x.insert(4, 40).is_ok());
+        let ret = x.lookup(&4);
+        assert!(ret.is_ok());
+        assert_eq!(ret.unwrap(), 40);
+        assert!(x.insert(5, 50).is_err());
+    }
+
+    #[test]
+    fn can_lookup_past_deleted_slot_in_cluster() {
+        let mut x = BasicHashBuilder::<u16, u16>::new().with_capacity(7).build();
+
+        // find three keys with the same home slot so they form one cluster
+        let home = x.hash(&0);
+        let keys: Vec<u16> = (0..u16::MAX)
+            .filter(|k| x.hash(k) == home)
+            .take(3)
+            .collect();
+        for (i, key) in keys.iter().enumerate() {
+            assert!(x.insert(*key, i as u16).is_ok());
+        }
+
+        // delete the head and the middle of the cluster
+        assert!(x.delete(&keys[0]).is_ok());
+        let ret = x.lookup(&keys[2]);
+        assert!(ret.is_ok());
+        assert_eq!(ret.unwrap(), 2);
+        assert!(x.delete(&keys[1]).is_ok());
+        let ret = x.lookup(&keys[2]);
+        assert!(ret.is_ok());
+        assert_eq!(ret.unwrap(), 2);
+        assert!(x.lookup(&keys[0]).is_err());
+        assert!(x.lookup(&keys[1]).is_err());
+
+        // reinserting fills the first deleted slot and keeps the cluster intact
+        assert!(x.insert(keys[1], 11).is_ok());
+        let ret = x.lookup(&keys[1]);
+        assert!(ret.is_ok());
+        assert_eq!(ret.unwrap(), 11);
+        let ret = x.lookup(&keys[2]);
+        assert!(ret.is_ok());
+        assert_eq!(ret.unwrap(), 2);
+        assert!(x.delete(&keys[2]).is_ok());
+        assert!(x.lookup(&keys[2]).is_err());
+        let ret = x.lookup(&keys[1]);
+        assert!(ret.is_ok());
+        assert_eq!(ret.unwrap(), 11);
+    }
+
+    #[test]
+    fn probe_sequences_visit_every_slot() {
+        let sequences = [
+            ProbeSequence::Linear,
+            ProbeSequence::Quadratic,
+            ProbeSequence::DoubleHashing,
+        ];
+        for sequence in sequences {
+            for capacity in [1, 2, 7, 10, 12, 16, 64] {
+                let x = BasicHashBuilder::<u16, u16>::new()
+                    .with_capacity(capacity)
+                    .with_probe_sequence(sequence)
+                    .build();
+                for key in 0..50 {
+                    let mut slots: Vec<usize> = x.probes(&key).collect();
+                    assert_eq!(slots[0], x.hash(&key));
+                    slots.sort();
+                    assert_eq!(slots, (0..x.get_capacity()).collect::<Vec<_>>());
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn quadratic_probing_keeps_power_of_two_capacity() {
+        let mut x = BasicHashBuilder::<u16, u16>::new()
+            .with_capacity(10)
+            .with_max_load_factor(0.75)
+            .with_min_load_factor(0.125)
+            .with_growth_factor(1.5)
+            .with_probe_sequence(ProbeSequence::Quadratic)
+            .build();
+        assert_eq!(x.get_capacity(), 16);
+        for key in 0..200 {
+            assert!(x.insert(key, key).is_ok());
+            assert!(x.get_capacity().is_power_of_two());
+        }
+        for key in 0..190 {
+            assert!(x.delete(&key).is_ok());
+            assert!(x.get_capacity().is_power_of_two());
+        }
+        assert!(x.get_capacity() < 256);
+        for key in 190..200 {
+            assert_eq!(x.get(&key), Some(&key));
+        }
+    }
+
+    #[test]
+    fn can_fill_and_delete_with_each_probe_sequence() {
+        let sequences = [
+            ProbeSequence::Linear,
+            ProbeSequence::Quadratic,
+            ProbeSequence::DoubleHashing,
+        ];
+        for sequence in sequences {
+            let mut x = BasicHashBuilder::<u16, u16>::new()
+                .with_capacity(32)
+                .with_probe_sequence(sequence)
+                .build();
+            for key in 0..32 {
+                assert!(x.insert(key, key * 10).is_ok());
+            }
+            assert!(matches!(x.insert(32, 0), Err(HashTableError::TableFull)));
+
+            // lookups probe past the tombstones deletes leave behind
+            for key in (0..32).step_by(3) {
+                assert!(x.delete(&key).is_ok());
+            }
+            for key in 0..32 {
+                let expected = (key % 3 != 0).then_some(key * 10);
+                assert_eq!(x.get(&key).copied(), expected);
+            }
+            for key in 100..111 {
+                assert!(x.insert(key, key).is_ok());
+            }
+            assert!(x.insert(111, 0).is_err());
+            for key in 100..111 {
+                assert_eq!(x.get(&key), Some(&key));
+            }
+        }
+    }
+
+    #[test]
+    fn can_grow_basic_hash() {
+        let mut x = BasicHashBuilder::<u16, u16>::new()
+            .with_capacity(3)
+            .with_max_load_factor(0.75)
+            .build();
+        for key in 0..100 {
+            assert!(x.insert(key, key * 10).is_ok());
+            assert!(x.len as f32 <= x.get_capacity() as f32 * 0.75);
+        }
+        assert_eq!(x.len, 100);
+        for key in 0..100 {
+            let ret = x.lookup(&key);
+            assert!(ret.is_ok());
+            assert_eq!(ret.unwrap(), key * 10);
+        }
+        assert!(x.lookup(&100).is_err());
+    }
+
+    #[test]
+    fn can_grow_and_shrink_basic_hash() {
+        let mut x = BasicHashBuilder::<u16, u16>::new()
+            .with_max_load_factor(0.5)
+            .with_min_load_factor(0.125)
+            .with_growth_factor(4.0)
+            .build();
+        assert_eq!(x.get_capacity(), 0);
+        for key in 0..64 {
+            assert!(x.insert(key, key).is_ok());
+        }
+        let grown = x.get_capacity();
+        assert!(grown >= 128);
+
+        for key in 0..60 {
+            assert!(x.delete(&key).is_ok());
+        }
+        assert!(x.get_capacity() < grown);
+        assert!(x.len as f32 <= x.get_capacity() as f32 * 0.5);
+        for key in 0..60 {
+            assert!(x.lookup(&key).is_err());
+        }
+        for key in 60..64 {
+            let ret = x.lookup(&key);
+            assert!(ret.is_ok());
+            assert_eq!(ret.unwrap(), key);
+        }
+    }
+
+    #[test]
+    fn tombstones_are_dropped_by_rehash() {
+        let mut x = BasicHashBuilder::<u16, u16>::new()
+            .with_capacity(8)
+            .with_max_load_factor(0.5)
+            .build();
+        // churn through many keys while holding only a few at a time, the
+        // capacity should not grow because of the tombstones left behind
+        for key in 0..200 {
+            assert!(x.insert(key, key).is_ok());
+            if key >= 2 {
+                assert!(x.delete(&(key - 2)).is_ok());
+            }
+        }
+        assert_eq!(x.get_capacity(), 8);
+        assert_eq!(x.len, 2);
+        assert!(x.lookup(&198).is_ok());
+        assert!(x.lookup(&199).is_ok());
+    }
+
+    #[test]
+    fn updating_existing_key_does_not_resize() {
+        let mut x = BasicHashBuilder::<u16, u16>::new()
+            .with_capacity(4)
+            .with_max_load_factor(0.5)
+            .build();
+        assert!(x.insert(1, 10).is_ok());
+        assert!(x.insert(2, 20).is_ok());
+        // the table is at its maximum load, one more key would grow it
+        for data in 11..20 {
+            assert_eq!(x.insert(1, data).unwrap(), Some(data - 1));
+        }
+        assert_eq!(x.get_capacity(), 4);
+        assert!(x.insert(3, 30).is_ok());
+        assert!(x.get_capacity() > 4);
+    }
+
+    #[test]
+    fn full_table_has_entries_for_existing_keys_only() {
+        let mut x = BasicHashBuilder::<u16, u16>::new().with_capacity(3).build();
+        for key in 1..4 {
+            assert!(x.insert(key, key * 10).is_ok());
+        }
+        assert!(matches!(x.entry(4), Err(HashTableError::TableFull)));
+        assert_eq!(*x.entry(2).unwrap().or_insert(0), 20);
+        match x.entry(2).unwrap() {
+            Entry::Occupied(entry) => assert_eq!(entry.remove(), 20),
+            Entry::Vacant(_) => panic!("key 2 should be occupied"),
+        }
+        *x.entry(4).unwrap().or_insert(40) += 4;
+        assert_eq!(x.get(&4), Some(&44));
+    }
+
+    #[test]
+    fn can_iterate_basic_hash() {
+        let mut x = BasicHashBuilder::<u16, u16>::new().with_capacity(8).build();
+        for key in 0..6 {
+            assert!(x.insert(key, key * 10).is_ok());
+        }
+        assert!(x.delete(&3).is_ok());
+
+        let mut entries: Vec<(u16, u16)> = x.iter().map(|(k, v)| (*k, *v)).collect();
+        entries.sort();
+        assert_eq!(entries, vec![(0, 0), (1, 10), (2, 20), (4, 40), (5, 50)]);
+        let mut keys: Vec<u16> = x.keys().copied().collect();
+        keys.sort();
+        assert_eq!(keys, vec![0, 1, 2, 4, 5]);
+        assert_eq!(x.values().sum::<u16>(), 120);
+
+        for (key, data) in x.iter_mut() {
+            *data += key;
+        }
+        for (_, data) in &mut x {
+            *data += 1;
+        }
+        assert_eq!((&x).into_iter().count(), 5);
+        assert_eq!(x.get(&5), Some(&56));
+
+        let mut drained: Vec<(u16, u16)> = x.drain().collect();
+        drained.sort();
+        assert_eq!(drained, vec![(0, 1), (1, 12), (2, 23), (4, 45), (5, 56)]);
+        assert_eq!(x.iter().count(), 0);
+        assert_eq!(x.get_capacity(), 8);
+        assert!(x.get(&5).is_none());
+
+        // the table is usable after a drain, even a partial one
+        assert!(x.insert(7, 70).is_ok());
+        assert!(x.insert(8, 80).is_ok());
+        assert_eq!(x.drain().take(1).count(), 1);
+        assert!(x.get(&7).is_none() && x.get(&8).is_none());
+        assert!(x.insert(9, 90).is_ok());
+
+        let owned: Vec<(u16, u16)> = x.into_iter().collect();
+        assert_eq!(owned, vec![(9, 90)]);
+    }
+
+    #[test]
+    fn seeded_hasher_puts_keys_in_the_same_slots() {
+        let builder = || {
+            BasicHashBuilder::<u16, u16>::new()
+                .with_capacity(8)
+                .with_hasher(MultiplyShift::with_seed(7))
+        };
+        let x = builder().build();
+        let y = builder().build();
+        assert!((0..100).all(|key| x.hash(&key) == y.hash(&key)));
     }
 }
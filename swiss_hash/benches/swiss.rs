@@ -0,0 +1,76 @@
+use criterion::{criterion_group, criterion_main, BenchmarkId, Criterion};
+use hash_table::hashers::XxBuildHasher;
+use hash_table::HashTable;
+use simple_hash::{BasicHash, BasicHashBuilder};
+use swiss_hash::{SwissHash, SwissHashBuilder};
+
+const CAPACITY: usize = 4096;
+const LOADS: [f64; 3] = [0.5, 0.75, 0.875];
+
+// both tables get CAPACITY slots, never grow, and hash with the same
+// function so only probing differs
+fn basic() -> BasicHash<u64, u64, XxBuildHasher> {
+    BasicHashBuilder::new()
+        .with_capacity(CAPACITY)
+        .with_hasher(XxBuildHasher::with_seed(1))
+        .build()
+}
+
+fn swiss() -> SwissHash<u64, u64, XxBuildHasher> {
+    SwissHashBuilder::new()
+        .with_capacity(CAPACITY)
+        .with_hasher(XxBuildHasher::with_seed(1))
+        .build()
+}
+
+fn keys(load: f64) -> u64 {
+    (CAPACITY as f64 * load) as u64
+}
+
+fn fill<T: HashTable<u64, u64>>(mut x: T, load: f64) -> T {
+    for key in 0..keys(load) {
+        x.insert(key, key).unwrap();
+    }
+    x
+}
+
+fn insert(c: &mut Criterion) {
+    let mut group = c.benchmark_group("insert");
+    for load in LOADS {
+        group.bench_with_input(BenchmarkId::new("basic", load), &load, |b, load| {
+            b.iter(|| fill(basic(), *load).len())
+        });
+        group.bench_with_input(BenchmarkId::new("swiss", load), &load, |b, load| {
+            b.iter(|| fill(swiss(), *load).len())
+        });
+    }
+    group.finish();
+}
+
+// look up every key held, or as many keys that are not
+fn lookups<T: HashTable<u64, u64>>(x: &T, load: f64, hit: bool) -> usize {
+    let offset = if hit { 0 } else { u64::MAX / 2 };
+    (0..keys(load))
+        .filter(|key| x.get(&(key + offset)).is_some())
+        .count()
+}
+
+fn lookup(c: &mut Criterion) {
+    for (name, hit) in [("lookup_hit", true), ("lookup_miss", false)] {
+        let mut group = c.benchmark_group(name);
+        for load in LOADS {
+            let x = fill(basic(), load);
+            group.bench_with_input(BenchmarkId::new("basic", load), &load, |b, load| {
+                b.iter(|| lookups(&x, *load, hit))
+            });
+            let x = fill(swiss(), load);
+            group.bench_with_input(BenchmarkId::new("swiss", load), &load, |b, load| {
+                b.iter(|| lookups(&x, *load, hit))
+            });
+        }
+        group.finish();
+    }
+}
+
+criterion_group!(benches, insert, lookup);
+criterion_main!(benches);
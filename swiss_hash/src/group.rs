@@ -0,0 +1,136 @@
+//! control bytes and the groups of GROUP_WIDTH of them a SwissHash probes
+//! at once. A control byte is EMPTY, DELETED, or the 7-bit tag of the
+//! entry in its slot, so the top bit alone tells a full slot from a free
+//! one. Groups are matched with SSE2 where it is available, and a byte
+//! at a time everywhere else
+
+pub(crate) const GROUP_WIDTH: usize = 16;
+
+/// a slot that has never held an entry since the table was last rebuilt
+pub(crate) const EMPTY: u8 = 0b1111_1111;
+/// a slot whose entry was removed, probing has to continue past it
+pub(crate) const DELETED: u8 = 0b1000_0000;
+
+/// the control byte of a full slot, the top 7 bits of its entry's hash
+pub(crate) fn tag(hash: u64) -> u8 {
+    (hash >> 57) as u8
+}
+
+pub(crate) fn is_full(ctrl: u8) -> bool {
+    ctrl & 0x80 == 0
+}
+
+/// one bit per slot of a group, set for the slots that matched.
+/// Iterating yields the offsets of the set bits in the group, lowest
+/// first
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub(crate) struct BitMask(pub(crate) u16);
+
+impl BitMask {
+    pub(crate) fn any(self) -> bool {
+        self.0 != 0
+    }
+
+    pub(crate) fn lowest(self) -> Option<usize> {
+        self.any().then(|| self.0.trailing_zeros() as usize)
+    }
+}
+
+impl Iterator for BitMask {
+    type Item = usize;
+
+    fn next(&mut self) -> Option<usize> {
+        let bit = self.lowest()?;
+        self.0 &= self.0 - 1;
+        Some(bit)
+    }
+}
+
+#[cfg(all(target_arch = "x86_64", target_feature = "sse2"))]
+pub(crate) use sse2::Group;
+
+#[cfg(not(all(target_arch = "x86_64", target_feature = "sse2")))]
+pub(crate) use generic::Group;
+
+#[cfg(all(target_arch = "x86_64", target_feature = "sse2"))]
+pub(crate) mod sse2 {
+    use super::{BitMask, EMPTY, GROUP_WIDTH};
+    use std::arch::x86_64::{
+        __m128i, _mm_cmpeq_epi8, _mm_loadu_si128, _mm_movemask_epi8, _mm_set1_epi8,
+    };
+
+    /// GROUP_WIDTH control bytes in an SSE2 register, matched against a
+    /// byte with one compare and a movemask
+    #[derive(Clone, Copy)]
+    pub(crate) struct Group(__m128i);
+
+    impl Group {
+        /// load the group starting at the front of ctrl
+        pub(crate) fn load(ctrl: &[u8]) -> Group {
+            let ctrl = &ctrl[..GROUP_WIDTH];
+            // SAFETY: ctrl holds GROUP_WIDTH bytes, exactly the 128 bits
+            // read, and loadu has no alignment requirement
+            Group(unsafe { _mm_loadu_si128(ctrl.as_ptr() as *const __m128i) })
+        }
+
+        pub(crate) fn match_byte(self, byte: u8) -> BitMask {
+            // SAFETY: SSE2 is enabled for the whole build, which is all
+            // these intrinsics need
+            unsafe {
+                let matches = _mm_cmpeq_epi8(self.0, _mm_set1_epi8(byte as i8));
+                BitMask(_mm_movemask_epi8(matches) as u16)
+            }
+        }
+
+        pub(crate) fn match_empty(self) -> BitMask {
+            self.match_byte(EMPTY)
+        }
+
+        /// free slots are the ones with the top bit of their control
+        /// byte set, which is just what movemask gathers
+        pub(crate) fn match_empty_or_deleted(self) -> BitMask {
+            // SAFETY: as for match_byte
+            BitMask(unsafe { _mm_movemask_epi8(self.0) } as u16)
+        }
+    }
+}
+
+pub(crate) mod generic {
+    use super::{is_full, BitMask, EMPTY, GROUP_WIDTH};
+
+    /// GROUP_WIDTH control bytes matched one at a time, for targets
+    /// without SSE2
+    #[derive(Clone, Copy)]
+    pub(crate) struct Group([u8; GROUP_WIDTH]);
+
+    impl Group {
+        /// load the group starting at the front of ctrl
+        pub(crate) fn load(ctrl: &[u8]) -> Group {
+            let mut bytes = [0; GROUP_WIDTH];
+            bytes.copy_from_slice(&ctrl[..GROUP_WIDTH]);
+            Group(bytes)
+        }
+
+        fn matching<F: Fn(u8) -> bool>(self, f: F) -> BitMask {
+            let mut mask = 0;
+            for (i, ctrl) in self.0.iter().enumerate() {
+                if f(*ctrl) {
+                    mask |= 1 << i;
+                }
+            }
+            BitMask(mask)
+        }
+
+        pub(crate) fn match_byte(self, byte: u8) -> BitMask {
+            self.matching(|ctrl| ctrl == byte)
+        }
+
+        pub(crate) fn match_empty(self) -> BitMask {
+            self.match_byte(EMPTY)
+        }
+
+        pub(crate) fn match_empty_or_deleted(self) -> BitMask {
+            self.matching(|ctrl| !is_full(ctrl))
+        }
+    }
+}
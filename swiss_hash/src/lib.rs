@@ -0,0 +1,544 @@
+#![allow(dead_code)]
+
+use group::{tag, Group, DELETED, EMPTY, GROUP_WIDTH};
+use hash_table::{HashFn, HashTable, HashTableEntry, HashTableError};
+use std::collections::hash_map::RandomState;
+use std::hash::Hash;
+use std::marker::PhantomData;
+
+mod entry;
+mod group;
+mod iter;
+
+pub use entry::{Entry, OccupiedEntry, VacantEntry};
+pub use hash_table::entry::{Occupied, Vacant};
+pub use iter::{Drain, IntoIter, Iter, IterMut, Keys, Values};
+
+/// an open addressing table in the style of SwissTable. Each slot has a
+/// control byte, kept apart from the slots, holding 7 bits of its
+/// entry's hash. Probing matches a whole group of control bytes against
+/// the tag of the key at once, and only compares keys in slots whose
+/// tag matched
+pub struct SwissHash<K, U, S = RandomState> {
+    data: Vec<HashTableEntry<K, U>>,
+    // one control byte per slot, EMPTY, DELETED, or the tag of the
+    // entry in the slot
+    ctrl: Vec<u8>,
+    // number of slots, zero or a power of two number of groups
+    capacity: usize,
+    // number of slots holding an entry
+    len: usize,
+    // number of slots marked DELETED
+    tombstones: usize,
+    // grow once entries plus tombstones would exceed this fraction of
+    // capacity, None disables growing
+    max_load_factor: Option<f32>,
+    // shrink once entries fall below this fraction of capacity, None
+    // disables shrinking
+    min_load_factor: Option<f32>,
+    // hashes keys, the low bits pick the first group probed and the top
+    // 7 bits are the tag
+    hash_builder: S,
+}
+
+/// the number of slots a table asked to hold capacity slots gets, a
+/// power of two number of groups
+fn slots_for(capacity: usize) -> usize {
+    if capacity == 0 {
+        return 0;
+    }
+    capacity.next_power_of_two().max(GROUP_WIDTH)
+}
+
+impl<K, U, S> SwissHash<K, U, S> {
+    fn get_capacity(&self) -> usize {
+        self.capacity
+    }
+
+    fn groups(&self) -> usize {
+        self.capacity / GROUP_WIDTH
+    }
+
+    fn group(&self, g: usize) -> Group {
+        Group::load(&self.ctrl[g * GROUP_WIDTH..])
+    }
+
+    /// the groups probed for a hash, in order. Triangular steps over a
+    /// power of two number of groups visit every group exactly once
+    fn probe_groups(&self, hash: u64) -> impl Iterator<Item = usize> {
+        let mask = self.groups() - 1;
+        let mut g = hash as usize & mask;
+        (0..self.groups()).map(move |step| {
+            g = (g + step) & mask;
+            g
+        })
+    }
+}
+
+impl<K: Hash + Eq + Default, U, S: HashFn<K>> SwissHash<K, U, S> {
+    /// get the entry for key for in-place manipulation. Room for an
+    /// absent key is made up front, so this fails with TableFull if the
+    /// key cannot be added. An existing key never resizes the table
+    pub fn entry(&mut self, key: K) -> Result<Entry<'_, K, U, S>, HashTableError> {
+        let hash = self.hash(&key);
+        let mut free = match self.probe(&key, hash) {
+            Ok(slot) => return Ok(Entry::Occupied(OccupiedEntry::new(self, slot))),
+            Err(free) => free,
+        };
+        if self.reserve_one() {
+            free = self.find_free_slot(hash);
+        }
+        match free {
+            Some(slot) => Ok(Entry::Vacant(VacantEntry::new(
+                self,
+                key,
+                (slot, tag(hash)),
+            ))),
+            None => Err(HashTableError::TableFull),
+        }
+    }
+}
+
+impl<K: Hash + Eq + Default, U, S: HashFn<K>> SwissHash<K, U, S> {
+    fn find_slot(&self, key: &K) -> Option<usize> {
+        self.probe(key, self.hash(key)).ok()
+    }
+
+    /// probe for key a group at a time, returning Ok with the slot
+    /// holding it, or Err with the first free slot seen along the way,
+    /// which is where key would be inserted. A group with an EMPTY slot
+    /// ends the probe, as key would have been inserted there
+    fn probe(&self, key: &K, hash: u64) -> Result<usize, Option<usize>> {
+        if self.get_capacity() == 0 {
+            return Err(None);
+        }
+        let tag = tag(hash);
+        let mut free = None;
+        for g in self.probe_groups(hash) {
+            let group = self.group(g);
+            let base = g * GROUP_WIDTH;
+            for bit in group.match_byte(tag) {
+                if self.data[base + bit].key == *key {
+                    return Ok(base + bit);
+                }
+            }
+            if free.is_none() {
+                free = group
+                    .match_empty_or_deleted()
+                    .lowest()
+                    .map(|bit| base + bit);
+            }
+            if group.match_empty().any() {
+                return Err(free);
+            }
+        }
+        Err(free)
+    }
+
+    /// the first free slot along the probe sequence of hash, deleted
+    /// slots are free and get reused
+    fn find_free_slot(&self, hash: u64) -> Option<usize> {
+        if self.get_capacity() == 0 {
+            return None;
+        }
+        self.probe_groups(hash).find_map(|g| {
+            let bit = self.group(g).match_empty_or_deleted().lowest()?;
+            Some(g * GROUP_WIDTH + bit)
+        })
+    }
+
+    fn place(&mut self, y: usize, tag: u8, key: K, data: Box<U>) {
+        if self.ctrl[y] == DELETED {
+            self.tombstones -= 1;
+        }
+        self.ctrl[y] = tag;
+        self.data[y].key = key;
+        self.data[y].data = Some(data);
+        self.len += 1;
+    }
+
+    /// empty the occupied slot y and return its data. The slot is only
+    /// marked DELETED if its group has no EMPTY slot, as then probes
+    /// may have gone past it. Otherwise no probe ever went further and
+    /// the slot can be EMPTY again
+    fn take(&mut self, y: usize) -> Box<U> {
+        let data = self.data[y].data.take().unwrap();
+        if self.group(y / GROUP_WIDTH).match_empty().any() {
+            self.ctrl[y] = EMPTY;
+        } else {
+            self.ctrl[y] = DELETED;
+            self.tombstones += 1;
+        }
+        self.len -= 1;
+        self.shrink_to_fit_load();
+        data
+    }
+
+    /// double the table, or rehash it in place to drop tombstones, if
+    /// one more entry would exceed the maximum load factor. Returns
+    /// whether the table was rebuilt
+    fn reserve_one(&mut self) -> bool {
+        let max_load_factor = match self.max_load_factor {
+            Some(max_load_factor) => max_load_factor,
+            None => return false,
+        };
+        let limit = self.get_capacity() as f32 * max_load_factor;
+        if ((self.len + self.tombstones + 1) as f32) <= limit {
+            return false;
+        }
+        if ((self.len + 1) as f32) <= limit {
+            self.resize(self.get_capacity());
+        } else {
+            self.resize((self.get_capacity() * 2).max(GROUP_WIDTH));
+        }
+        true
+    }
+
+    /// halve the table if entries have fallen below the minimum load
+    /// factor, as long as the result stays under the maximum load
+    /// factor and holds at least one group
+    fn shrink_to_fit_load(&mut self) {
+        let min_load_factor = match self.min_load_factor {
+            Some(min_load_factor) => min_load_factor,
+            None => return,
+        };
+        if (self.len as f32) >= self.get_capacity() as f32 * min_load_factor {
+            return;
+        }
+        let capacity = self.get_capacity() / 2;
+        let max_load_factor = self.max_load_factor.unwrap_or(1.0);
+        if capacity >= GROUP_WIDTH && (self.len as f32) < capacity as f32 * max_load_factor {
+            self.resize(capacity);
+        }
+    }
+
+    /// replace the slots with empty ones of the given capacity, handing
+    /// back the old slots
+    fn reset(&mut self, capacity: usize) -> Vec<HashTableEntry<K, U>> {
+        let mut data = Vec::with_capacity(capacity);
+        for _i in 0..capacity {
+            data.push(HashTableEntry::<K, U>::default());
+        }
+        self.ctrl = vec![EMPTY; capacity];
+        self.capacity = capacity;
+        self.len = 0;
+        self.tombstones = 0;
+        std::mem::replace(&mut self.data, data)
+    }
+
+    /// move every entry into a table of the given capacity, dropping
+    /// all tombstones. Entries keep their boxed data, only the slot
+    /// they live in changes
+    fn resize(&mut self, capacity: usize) {
+        let old = self.reset(capacity);
+        for entry in old {
+            if let Some(data) = entry.data {
+                // capacity is never below len, so a slot is always found
+                let hash = self.hash(&entry.key);
+                let y = self.find_free_slot(hash).unwrap();
+                self.place(y, tag(hash), entry.key, data);
+            }
+        }
+    }
+}
+
+pub struct SwissHashBuilder<K, U, S = RandomState> {
+    _phantom_k: PhantomData<K>,
+    _phantom_u: PhantomData<U>,
+    capacity: usize,
+    max_load_factor: Option<f32>,
+    min_load_factor: Option<f32>,
+    hash_builder: S,
+}
+
+impl<K: Default, U> SwissHashBuilder<K, U> {
+    pub fn new() -> SwissHashBuilder<K, U> {
+        SwissHashBuilder::<K, U> {
+            _phantom_k: PhantomData {},
+            _phantom_u: PhantomData {},
+            capacity: 0,
+            max_load_factor: None,
+            min_load_factor: None,
+            hash_builder: RandomState::new(),
+        }
+    }
+}
+
+impl<K: Default, U> Default for SwissHashBuilder<K, U> {
+    fn default() -> SwissHashBuilder<K, U> {
+        SwissHashBuilder::new()
+    }
+}
+
+impl<K: Default, U, S> SwissHashBuilder<K, U, S> {
+    /// number of slots, rounded up to a power of two number of groups
+    pub fn with_capacity(mut self, capacity: usize) -> SwissHashBuilder<K, U, S> {
+        self.capacity = capacity;
+        self
+    }
+
+    /// double the table once it is more than load_factor full, instead
+    /// of failing inserts with TableFull. load_factor is in (0, 1]
+    pub fn with_max_load_factor(mut self, load_factor: f32) -> SwissHashBuilder<K, U, S> {
+        assert!(
+            load_factor > 0.0 && load_factor <= 1.0,
+            "max load factor must be in (0, 1]"
+        );
+        self.max_load_factor = Some(load_factor);
+        self
+    }
+
+    /// halve the table once it is less than load_factor full
+    pub fn with_min_load_factor(mut self, load_factor: f32) -> SwissHashBuilder<K, U, S> {
+        assert!(
+            (0.0..1.0).contains(&load_factor),
+            "min load factor must be in [0, 1)"
+        );
+        self.min_load_factor = Some(load_factor);
+        self
+    }
+
+    /// hash keys with hash_builder rather than a randomly keyed SipHash.
+    /// Any BuildHasher will do, as will the hashers in hash_table::hashers
+    pub fn with_hasher<T>(self, hash_builder: T) -> SwissHashBuilder<K, U, T> {
+        SwissHashBuilder::<K, U, T> {
+            _phantom_k: PhantomData {},
+            _phantom_u: PhantomData {},
+            capacity: self.capacity,
+            max_load_factor: self.max_load_factor,
+            min_load_factor: self.min_load_factor,
+            hash_builder,
+        }
+    }
+
+    pub fn build(self) -> SwissHash<K, U, S> {
+        let capacity = slots_for(self.capacity);
+        let mut hash = SwissHash::<K, U, S> {
+            data: Vec::with_capacity(capacity),
+            ctrl: vec![EMPTY; capacity],
+            capacity,
+            len: 0,
+            tombstones: 0,
+            max_load_factor: self.max_load_factor,
+            min_load_factor: self.min_load_factor,
+            hash_builder: self.hash_builder,
+        };
+
+        // initialize the hash table
+        for _i in 0..capacity {
+            let h = HashTableEntry::<K, U>::default();
+            hash.data.push(h);
+        }
+        hash
+    }
+}
+
+impl<K: Hash + Eq + Default, U, S: HashFn<K>> HashTable<K, U> for SwissHash<K, U, S> {
+    fn insert(&mut self, key: K, data: U) -> Result<Option<U>, HashTableError> {
+        match self.entry(key)? {
+            Entry::Occupied(mut entry) => Ok(Some(entry.insert(data))),
+            Entry::Vacant(entry) => {
+                entry.insert(data);
+                Ok(None)
+            }
+        }
+    }
+
+    /// remove clears the slot, marking it DELETED only if probes may
+    /// have gone past it
+    fn remove(&mut self, key: &K) -> Result<U, HashTableError> {
+        let y = self.find_slot(key).ok_or(HashTableError::NotFound)?;
+        Ok(*self.take(y))
+    }
+
+    fn get(&self, key: &K) -> Option<&U> {
+        let y = self.find_slot(key)?;
+        self.data[y].data.as_deref()
+    }
+
+    fn get_mut(&mut self, key: &K) -> Option<&mut U> {
+        let y = self.find_slot(key)?;
+        self.data[y].data.as_deref_mut()
+    }
+
+    fn len(&self) -> usize {
+        self.len
+    }
+
+    fn clear(&mut self) {
+        self.reset(self.get_capacity());
+    }
+
+    fn capacity(&self) -> usize {
+        self.get_capacity()
+    }
+}
+
+impl<K, U, S: HashFn<K>> SwissHash<K, U, S> {
+    /// the full hash of key, split into the first group probed and the
+    /// tag
+    fn hash(&self, key: &K) -> u64 {
+        self.hash_builder.hash(key)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use group::is_full;
+    use hash_table::hashers::FnvBuildHasher;
+    use hash_table::testing::{check_table, TableBuilder};
+
+    struct Builder;
+
+    impl TableBuilder for Builder {
+        type Table<K: Hash + Eq + Default, U, S: HashFn<K>> = SwissHash<K, U, S>;
+
+        fn build<K: Hash + Eq + Default, U, S: HashFn<K>>(
+            &self,
+            hash_builder: S,
+        ) -> SwissHash<K, U, S> {
+            SwissHashBuilder::new()
+                .with_capacity(16)
+                .with_max_load_factor(0.875)
+                .with_min_load_factor(0.25)
+                .with_hasher(hash_builder)
+                .build()
+        }
+    }
+
+    #[test]
+    fn passes_table_tests() {
+        check_table(Builder);
+    }
+
+    // every full control byte is the tag of the entry in its slot, and
+    // the counts of full and DELETED bytes match len and tombstones
+    fn check_ctrl<K: Hash + Eq + Default, U, S: HashFn<K>>(x: &SwissHash<K, U, S>) {
+        let mut full = 0;
+        let mut deleted = 0;
+        for y in 0..x.get_capacity() {
+            if is_full(x.ctrl[y]) {
+                assert!(x.data[y].data.is_some());
+                assert_eq!(x.ctrl[y], tag(x.hash(&x.data[y].key)));
+                full += 1;
+            } else {
+                assert!(x.data[y].data.is_none());
+                if x.ctrl[y] == DELETED {
+                    deleted += 1;
+                }
+            }
+        }
+        assert_eq!(full, x.len);
+        assert_eq!(deleted, x.tombstones);
+    }
+
+    #[test]
+    fn can_lookup_past_full_group() {
+        let mut x = SwissHashBuilder::<u16, u16>::new()
+            .with_capacity(2 * GROUP_WIDTH)
+            .with_hasher(FnvBuildHasher)
+            .build();
+
+        // find more keys starting in the first group than it can hold, so
+        // the rest overflow into the second
+        let keys: Vec<u16> = (0..u16::MAX)
+            .filter(|k| x.probe_groups(x.hash(k)).next() == Some(0))
+            .take(GROUP_WIDTH + 4)
+            .collect();
+        for (i, key) in keys.iter().enumerate() {
+            assert!(x.insert(*key, i as u16).is_ok());
+        }
+        assert!(x.group(0).match_empty_or_deleted().next().is_none());
+        for (i, key) in keys.iter().enumerate() {
+            assert_eq!(x.get(key), Some(&(i as u16)));
+        }
+
+        // probes went past the first group, so deleting from it leaves a
+        // tombstone, while the second group still has EMPTY slots
+        assert!(x.delete(&keys[0]).is_ok());
+        assert_eq!(x.tombstones, 1);
+        assert!(x.delete(&keys[GROUP_WIDTH]).is_ok());
+        assert_eq!(x.tombstones, 1);
+        for (i, key) in keys.iter().enumerate().skip(1) {
+            if i != GROUP_WIDTH {
+                assert_eq!(x.get(key), Some(&(i as u16)));
+            }
+        }
+        assert!(x.get(&keys[0]).is_none());
+        assert!(x.get(&keys[GROUP_WIDTH]).is_none());
+        check_ctrl(&x);
+
+        // reinserting fills the tombstone in the first group
+        assert!(x.insert(keys[GROUP_WIDTH], 100).is_ok());
+        assert!(x.find_slot(&keys[GROUP_WIDTH]).unwrap() < GROUP_WIDTH);
+        assert_eq!(x.tombstones, 0);
+        check_ctrl(&x);
+    }
+
+    #[test]
+    fn group_implementations_agree() {
+        // every kind of control byte, in a few arrangements
+        let mut ctrl: Vec<u8> = (0..=0x7f).collect();
+        ctrl.extend([EMPTY; 24]);
+        ctrl.extend([DELETED; 24]);
+        ctrl.extend([0x5a, EMPTY, DELETED, 0x00].repeat(8));
+        for start in (0..ctrl.len() - GROUP_WIDTH).step_by(5) {
+            let bytes = &ctrl[start..];
+            let group = Group::load(bytes);
+            let generic = group::generic::Group::load(bytes);
+            for byte in [0x00, 0x5a, 0x7f, 0x20, EMPTY, DELETED] {
+                assert_eq!(group.match_byte(byte), generic.match_byte(byte));
+                let expected: Vec<usize> = (0..GROUP_WIDTH).filter(|i| bytes[*i] == byte).collect();
+                assert_eq!(generic.match_byte(byte).collect::<Vec<_>>(), expected);
+            }
+            assert_eq!(group.match_empty(), generic.match_empty());
+            let free = group.match_empty_or_deleted();
+            assert_eq!(free, generic.match_empty_or_deleted());
+            for i in free {
+                assert!(!is_full(bytes[i]));
+            }
+        }
+    }
+
+    #[test]
+    fn tombstones_are_dropped_by_rehash() {
+        let mut x = SwissHashBuilder::<u16, u16>::new()
+            .with_capacity(GROUP_WIDTH)
+            .with_max_load_factor(0.5)
+            .build();
+        // churn through many keys while holding only a few at a time, the
+        // capacity should not grow because of the tombstones left behind
+        for key in 0..2000 {
+            assert!(x.insert(key, key).is_ok());
+            if key >= 2 {
+                assert!(x.delete(&(key - 2)).is_ok());
+            }
+        }
+        assert_eq!(x.get_capacity(), GROUP_WIDTH);
+        assert_eq!(x.len, 2);
+        assert!(x.lookup(&1998).is_ok());
+        assert!(x.lookup(&1999).is_ok());
+        check_ctrl(&x);
+    }
+
+    #[test]
+    fn updating_existing_key_does_not_resize() {
+        let mut x = SwissHashBuilder::<u16, u16>::new()
+            .with_capacity(GROUP_WIDTH)
+            .with_max_load_factor(0.5)
+            .build();
+        for key in 0..8 {
+            assert!(x.insert(key, key).is_ok());
+        }
+        // the table is at its maximum load, one more key would grow it
+        for data in 10..20 {
+            assert!(x.insert(1, data).is_ok());
+        }
+        assert_eq!(x.get_capacity(), GROUP_WIDTH);
+        assert_eq!(x.lookup(&1).unwrap(), 19);
+        assert!(x.insert(8, 8).is_ok());
+        assert_eq!(x.get_capacity(), 2 * GROUP_WIDTH);
+        check_ctrl(&x);
+    }
+}